@@ -1,7 +1,7 @@
 use std::{hint::black_box, time::Duration};
 use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
 
-use rust_algorithms::{solver_bf, solver_bt};
+use rust_algorithms::{options::{Heuristic, SolveOptions}, puzzle::Puzzle, solver_bf, solver_bt, solver_cp, solver_deduce, solver_lin};
 
 const INPUTS: &[&str] = &[
     ("I + BB == ILL"),
@@ -15,6 +15,13 @@ const INPUTS: &[&str] = &[
     ("AND + A + STRONG + OFFENSE + AS + A + GOOD == DEFENSE"),
 ];
 
+const LARGE_INPUTS: &[&str] = &[
+    ("AND + A + STRONG + OFFENSE + AS + A + GOOD == DEFENSE"),
+    ("SO + MANY + MORE + MEN + SEEM + TO + SAY + THAT + THEY + MAY + SOON + TRY + TO + STAY + AT + HOME + SO + AS + TO + SEE + OR + HEAR + THE + SAME + ONE + MAN + TRY + TO + MEET + THE + TEAM + ON + THE + MOON + AS + HE + HAS + AT + THE + OTHER + TEN == TESTS"),
+];
+
+const FORTRESSES: &str = "THIS + A + FIRE + THEREFORE + FOR + ALL + HISTORIES + I + TELL + A + TALE + THAT + FALSIFIES + ITS + TITLE + TIS + A + LIE + THE + TALE + OF + THE + LAST + FIRE + HORSES + LATE + AFTER + THE + FIRST + FATHERS + FORESEE + THE + HORRORS + THE + LAST + FREE + TROLL + TERRIFIES + THE + HORSES + OF + FIRE + THE + TROLL + RESTS + AT + THE + HOLE + OF + LOSSES + IT + IS + THERE + THAT + SHE + STORES + ROLES + OF + LEATHERS + AFTER + SHE + SATISFIES + HER + HATE + OFF + THOSE + FEARS + A + TASTE + RISES + AS + SHE + HEARS + THE + LEAST + FAR + HORSE + THOSE + FAST + HORSES + THAT + FIRST + HEAR + THE + TROLL + FLEE + OFF + TO + THE + FOREST + THE + HORSES + THAT + ALERTS + RAISE + THE + STARES + OF + THE + OTHERS + AS + THE + TROLL + ASSAILS + AT + THE + TOTAL + SHIFT + HER + TEETH + TEAR + HOOF + OFF + TORSO + AS + THE + LAST + HORSE + FORFEITS + ITS + LIFE + THE + FIRST + FATHERS + HEAR + OF + THE + HORRORS + THEIR + FEARS + THAT + THE + FIRES + FOR + THEIR + FEASTS + ARREST + AS + THE + FIRST + FATHERS + RESETTLE + THE + LAST + OF + THE + FIRE + HORSES + THE + LAST + TROLL + HARASSES + THE + FOREST + HEART + FREE + AT + LAST + OF + THE + LAST + TROLL + ALL + OFFER + THEIR + FIRE + HEAT + TO + THE + ASSISTERS + FAR + OFF + THE + TROLL + FASTS + ITS + LIFE + SHORTER + AS + STARS + RISE + THE + HORSES + REST + SAFE + AFTER + ALL + SHARE + HOT + FISH + AS + THEIR + AFFILIATES + TAILOR + A + ROOFS + FOR + THEIR + SAFE == FORTRESSES";
+
 fn solvers_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("Solvers 6 letters comparison");
     let loops: usize = 1;
@@ -29,6 +36,17 @@ fn solvers_benchmark(c: &mut Criterion) {
         });
     });
 
+    group.bench_with_input(BenchmarkId::new("Bruteforce parallel", loops), &loops, |b, &loops| {
+        b.iter(|| {
+            for _ in 0..loops {
+                for input in INPUTS {
+                    let puzzle = Puzzle::parse(black_box(input)).unwrap();
+                    _ = solver_bf::solve_parallel(&puzzle, &SolveOptions::default(), 0);
+                }
+            }
+        });
+    });
+
     group.bench_with_input(BenchmarkId::new("Backtracking", loops), &loops, |b, &loops| {
         b.iter(|| {
             for _ in 0..loops {
@@ -38,6 +56,80 @@ fn solvers_benchmark(c: &mut Criterion) {
             }
         });
     });
+
+    group.bench_with_input(BenchmarkId::new("Constraint propagation", loops), &loops, |b, &loops| {
+        b.iter(|| {
+            for _ in 0..loops {
+                for input in INPUTS {
+                    _ = solver_cp::solve(black_box(input));
+                }
+            }
+        });
+    });
+
+    group.bench_with_input(BenchmarkId::new("Linear coefficients", loops), &loops, |b, &loops| {
+        b.iter(|| {
+            for _ in 0..loops {
+                for input in INPUTS {
+                    _ = solver_lin::solve(black_box(input));
+                }
+            }
+        });
+    });
+
+    group.bench_with_input(BenchmarkId::new("Deduction", loops), &loops, |b, &loops| {
+        b.iter(|| {
+            for _ in 0..loops {
+                for input in INPUTS {
+                    _ = solver_deduce::solve(black_box(input));
+                }
+            }
+        });
+    });
+}
+
+fn backtracking_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Backtracking 10 letters");
+
+    // Run with `--features baseline` to compare with the backtracking cloning maps of digits
+    for input in LARGE_INPUTS {
+        #[cfg(feature = "baseline")]
+        group.bench_with_input(BenchmarkId::new("Backtracking baseline", input.len()), input, |b, input| {
+            b.iter(|| rust_algorithms::solver_bt_baseline::solve(black_box(input)));
+        });
+        group.bench_with_input(BenchmarkId::new("Backtracking", input.len()), input, |b, input| {
+            b.iter(|| solver_bt::solve(black_box(input)));
+        });
+    }
+}
+
+fn fortresses_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("FORTRESSES 199 addends");
+
+    group.bench_function("Backtracking", |b| {
+        b.iter(|| solver_bt::solve(black_box(FORTRESSES)));
+    });
+
+    group.bench_function("Constraint propagation", |b| {
+        b.iter(|| solver_cp::solve(black_box(FORTRESSES)));
+    });
+}
+
+fn heuristics_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Backtracking heuristics");
+    let heuristics = [Heuristic::Static, Heuristic::MinimumRemainingValues, Heuristic::LeadingFirst, Heuristic::LargestCoefficient];
+
+    for heuristic in heuristics {
+        let options = SolveOptions::new().heuristic(heuristic);
+        group.bench_with_input(BenchmarkId::new("Backtracking", format!("{:?}", heuristic)), &options, |b, options| {
+            b.iter(|| {
+                for input in INPUTS {
+                    let puzzle = Puzzle::parse(black_box(input)).unwrap();
+                    _ = solver_bt::solve_with(&puzzle, options);
+                }
+            });
+        });
+    }
 }
 
 fn configure_criterion() -> Criterion {
@@ -49,6 +141,6 @@ fn configure_criterion() -> Criterion {
 criterion_group!(
     name = benches;
     config = configure_criterion();
-    targets = solvers_benchmark
+    targets = solvers_benchmark, backtracking_benchmark, fortresses_benchmark, heuristics_benchmark
 );
 criterion_main!(benches);
\ No newline at end of file
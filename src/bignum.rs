@@ -0,0 +1,224 @@
+use std::{cmp::Ordering, fmt, ops::{Add, Mul, Neg, Sub}};
+
+/// Signed integer of any size, for values of words and terms which do not fit in primitive integers.
+/// Magnitude is kept in base 2^32 limbs, least significant first, without trailing zero limbs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self { Self::default() }
+
+    /// Value of digits in the base, most significant digit first.
+    pub fn from_digits(digits: impl IntoIterator<Item = u8>, base: u8) -> Self {
+        let mut value = Self::zero();
+        for digit in digits {
+            value.mul_add_small(base as u32, digit as u32);
+        }
+        value
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    fn mul_add_small(&mut self, factor: u32, addend: u32) {
+        let mut carry = addend as u64;
+        for limb in self.limbs.iter_mut() {
+            let value = *limb as u64 * factor as u64 + carry;
+            *limb = value as u32;
+            carry = value >> 32;
+        }
+        if carry != 0 {
+            self.limbs.push(carry as u32);
+        }
+        self.trim();
+    }
+
+    /// Divides the magnitude in place, returning the remainder.
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let value = remainder << 32 | *limb as u64;
+            *limb = (value / divisor as u64) as u32;
+            remainder = value % divisor as u64;
+        }
+        self.trim();
+        remainder as u32
+    }
+
+    fn trim(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        if self.limbs.is_empty() {
+            self.negative = false;
+        }
+    }
+
+    fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+        a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+    }
+
+    fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(a.len().max(b.len()) + 1);
+        let mut carry = 0u64;
+        for idx in 0..a.len().max(b.len()) {
+            let value = *a.get(idx).unwrap_or(&0) as u64 + *b.get(idx).unwrap_or(&0) as u64 + carry;
+            limbs.push(value as u32);
+            carry = value >> 32;
+        }
+        if carry != 0 {
+            limbs.push(carry as u32);
+        }
+        limbs
+    }
+
+    /// Magnitude of `a - b`, where `a` is not smaller than `b`.
+    fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(a.len());
+        let mut borrow = 0i64;
+        for (idx, &limb) in a.iter().enumerate() {
+            let mut value = limb as i64 - *b.get(idx).unwrap_or(&0) as i64 - borrow;
+            borrow = (value < 0) as i64;
+            value += borrow << 32;
+            limbs.push(value as u32);
+        }
+        limbs
+    }
+
+    fn with_sign(negative: bool, limbs: Vec<u32>) -> Self {
+        let mut value = Self { negative, limbs };
+        value.trim();
+        value
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::with_sign(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        Self::with_sign(!self.negative, self.limbs)
+    }
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return Self::with_sign(self.negative, Self::add_magnitude(&self.limbs, &other.limbs));
+        }
+        // Signs differ, so the bigger magnitude decides the sign
+        match Self::cmp_magnitude(&self.limbs, &other.limbs) {
+            Ordering::Less => Self::with_sign(other.negative, Self::sub_magnitude(&other.limbs, &self.limbs)),
+            _ => Self::with_sign(self.negative, Self::sub_magnitude(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: BigInt) -> BigInt {
+        self + -other
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: BigInt) -> BigInt {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let value = limbs[i + j] as u64 + a as u64 * b as u64 + carry;
+                limbs[i + j] = value as u32;
+                carry = value >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        Self::with_sign(self.negative != other.negative, limbs)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => Self::cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => Self::cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Groups of 9 decimal digits, least significant first
+        let mut magnitude = Self { negative: false, limbs: self.limbs.clone() };
+        let mut groups = vec![];
+        while !magnitude.is_zero() {
+            groups.push(magnitude.div_rem_small(1_000_000_000));
+        }
+        let sign = if self.negative { "-" } else { "" };
+        match groups.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}{}", sign, first)?;
+                rest.iter().rev().try_for_each(|group| write!(f, "{:09}", group))
+            }
+        }
+    }
+}
+
+#[test]
+fn value_of_digits() {
+    assert_eq!(BigInt::from_digits([9, 5, 6, 7], 10), BigInt::from(9567));
+    assert_eq!(BigInt::from_digits([1, 0, 1], 2), BigInt::from(5));
+    assert_eq!(BigInt::from_digits([], 10), BigInt::zero());
+    assert_eq!(BigInt::from_digits([0, 0, 7], 10).to_string(), "7");
+}
+
+#[test]
+fn arithmetic_beyond_primitive_integers() {
+    let big = BigInt::from_digits([9; 40], 10);
+    assert_eq!(big.to_string(), "9".repeat(40));
+    assert_eq!((big.clone() + BigInt::from(1)).to_string(), format!("1{}", "0".repeat(40)));
+    assert_eq!((big.clone() * big.clone()).to_string(), format!("{}8{}1", "9".repeat(39), "0".repeat(39)));
+    assert_eq!(big.clone() - big.clone(), BigInt::zero());
+    assert!(!(big.clone() - big).is_negative());
+}
+
+#[test]
+fn signed_arithmetic() {
+    let values = [-5_000_000_000i64, -7, -1, 0, 1, 3, 4_294_967_296, 9_000_000_000];
+    for &a in values.iter() {
+        for &b in values.iter() {
+            assert_eq!((BigInt::from(a) + BigInt::from(b)).to_string(), (a as i128 + b as i128).to_string());
+            assert_eq!((BigInt::from(a) - BigInt::from(b)).to_string(), (a as i128 - b as i128).to_string());
+            assert_eq!((BigInt::from(a) * BigInt::from(b)).to_string(), (a as i128 * b as i128).to_string());
+            assert_eq!(BigInt::from(a).cmp(&BigInt::from(b)), a.cmp(&b));
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, fs, io, path::PathBuf, sync::Arc, time::Duration};
+use itertools::Itertools;
+
+use crate::options::{LetterOrder, SolveOptions};
+use crate::puzzle::Puzzle;
+use crate::solution::Solution;
+use crate::stats::SolveStats;
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Any solver returning stats, like `solve_with_stats` of every solver module.
+type Solver = dyn Fn(&Puzzle, &SolveOptions) -> (Option<Solution>, SolveStats) + Send + Sync;
+
+/// Answer of the solver for a canonical puzzle, digits are of canonical letters.
+#[derive(Debug, Clone)]
+struct Entry {
+    solution: Option<Solution>,
+    stats: SolveStats,
+    last_used: u64,
+}
+
+/// Solver remembering its answers for the least recently used puzzles, optionally kept in a file between runs.
+/// Puzzles are looked up by their canonical form with the options, so renamed letters or reordered terms of a solved
+/// puzzle hit the cache too. The solver always solves the canonical puzzle and digits are renamed back, so a puzzle
+/// with several solutions gets the same one on a hit as on a miss. Letter orders other than the default one depend on
+/// names of the letters, puzzles solved with them go straight to the solver and are not cached.
+#[derive(Clone)]
+pub struct SolverCache {
+    solver: Arc<Solver>,
+    capacity: usize,
+    path: Option<PathBuf>,
+    entries: HashMap<String, Entry>,
+    // Keys by the last time they were used, least recently used first
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl SolverCache {
+    /// Wraps the solver, e.g. `solver_bt::solve_with_stats`, keeping up to 1024 puzzles.
+    pub fn new(solver: impl Fn(&Puzzle, &SolveOptions) -> (Option<Solution>, SolveStats) + Send + Sync + 'static) -> Self {
+        Self {
+            solver: Arc::new(solver),
+            capacity: DEFAULT_CAPACITY,
+            path: None,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Puzzles kept in memory and in the file, least recently used ones are dropped first.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self.evict();
+        self
+    }
+
+    /// File the cache is saved to by `save`, puzzles already in it are loaded. A file holds answers of a single solver,
+    /// lines which cannot be read are skipped.
+    pub fn file(mut self, path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                // Lines go from the least recently used one
+                for (key, entry) in content.lines().filter_map(parse_line) {
+                    self.insert(key, entry.solution, entry.stats);
+                }
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    pub fn solve(&mut self, puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, SolveStats) {
+        if options.letter_order != LetterOrder::default() {
+            self.misses += 1;
+            return (self.solver)(puzzle, options);
+        }
+        let (canonical, letters) = puzzle.canonical_letters();
+        let options = options.renamed(&letters);
+        let key = format!("{}\tbase {}\t{:?}", canonical, canonical.base, options);
+
+        self.clock += 1;
+        let (solution, stats) = match self.entries.get_mut(&key) {
+            Some(entry) => {
+                self.hits += 1;
+                self.recency.remove(&entry.last_used);
+                self.recency.insert(self.clock, key);
+                entry.last_used = self.clock;
+                (entry.solution.clone(), entry.stats.clone())
+            }
+            None => {
+                self.misses += 1;
+                let (solution, stats) = (self.solver)(&canonical, &options);
+                self.insert(key, solution.clone(), stats.clone());
+                (solution, stats)
+            }
+        };
+
+        let original: HashMap<char, char> = letters.into_iter().map(|(letter, canonical)| (canonical, letter)).collect();
+        (solution.map(|solution| solution.iter().map(|(letter, digit)| (original[&letter], digit)).collect()), stats)
+    }
+
+    /// Writes the cached puzzles to the file, if there is one.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content: String = self.recency.values()
+            .map(|key| {
+                let entry = &self.entries[key];
+                let solution = entry.solution.as_ref().map_or("-".to_string(), |solution| solution.iter().map(|(letter, digit)| format!("{}={}", letter, digit)).join(" "));
+                let stats = &entry.stats;
+                format!("{}\t{}\t{} {} {} {} {} {}\n", key, solution, stats.nodes, stats.permutations, stats.pruned_leading_zero,
+                    stats.pruned_column_mismatch, stats.max_depth, stats.elapsed.as_nanos())
+            })
+            .collect();
+        fs::write(path, content)
+    }
+
+    /// Solves answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Solves passed to the solver.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops all cached puzzles, counters are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    fn insert(&mut self, key: String, solution: Option<Solution>, stats: SolveStats) {
+        self.clock += 1;
+        if let Some(entry) = self.entries.insert(key.clone(), Entry { solution, stats, last_used: self.clock }) {
+            self.recency.remove(&entry.last_used);
+        }
+        self.recency.insert(self.clock, key);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity {
+            let (_, key) = self.recency.pop_first().unwrap();
+            self.entries.remove(&key);
+        }
+    }
+}
+
+impl fmt::Debug for SolverCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SolverCache")
+            .field("capacity", &self.capacity)
+            .field("path", &self.path)
+            .field("len", &self.entries.len())
+            .field("hits", &self.hits)
+            .field("misses", &self.misses)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Line written by `save` - key, digits or `-` without a solution, and stats, separated by tabs.
+fn parse_line(line: &str) -> Option<(String, Entry)> {
+    let mut parts = line.rsplitn(3, '\t');
+    let (stats, solution, key) = (parts.next()?, parts.next()?, parts.next()?);
+
+    let solution = match solution {
+        "-" => None,
+        "" => Some(Solution::default()),
+        digits => Some(digits.split(' ')
+            .map(|digit| {
+                let (letter, digit) = digit.split_once('=')?;
+                Some((letter.parse().ok()?, digit.parse().ok()?))
+            })
+            .collect::<Option<Solution>>()?),
+    };
+    let numbers = stats.split(' ').map(|number| number.parse::<u128>().ok()).collect::<Option<Vec<_>>>()?;
+    let [nodes, permutations, pruned_leading_zero, pruned_column_mismatch, max_depth, elapsed] = numbers[..] else {
+        return None;
+    };
+    let stats = SolveStats {
+        nodes: nodes as u64,
+        permutations: permutations as u64,
+        pruned_leading_zero: pruned_leading_zero as u64,
+        pruned_column_mismatch: pruned_column_mismatch as u64,
+        max_depth: max_depth as usize,
+        elapsed: Duration::from_nanos(elapsed as u64),
+    };
+    Some((key.to_string(), Entry { solution, stats, last_used: 0 }))
+}
+
+#[cfg(test)]
+fn cached_bt() -> SolverCache {
+    SolverCache::new(crate::solver_bt::solve_with_stats)
+}
+
+#[test]
+fn isomorphic_puzzles_hit_the_cache() {
+    let mut cache = cached_bt();
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let (solution, stats) = cache.solve(&puzzle, &SolveOptions::default());
+    assert_eq!(solution, crate::solver_bt::solve_puzzle(&puzzle));
+    assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+    let renamed = Puzzle::parse("NPSF + TFOE == NPOFZ").unwrap();
+    let (renamed_solution, renamed_stats) = cache.solve(&renamed, &SolveOptions::default());
+    assert!(renamed_solution.unwrap().verify(&renamed));
+    assert_eq!(renamed_stats, stats);
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (1, 1, 1));
+
+    // Unsolvable puzzles are cached too
+    let puzzle = Puzzle::parse("A + B == CDE").unwrap();
+    assert_eq!(cache.solve(&puzzle, &SolveOptions::default()).0, None);
+    assert_eq!(cache.solve(&puzzle, &SolveOptions::default()).0, None);
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (2, 2, 2));
+}
+
+#[test]
+fn options_are_part_of_the_key() {
+    let mut cache = SolverCache::new(crate::solver_cp::solve_with_stats);
+    let puzzle = Puzzle::parse("A + B == C").unwrap();
+    let (solution, _) = cache.solve(&puzzle, &SolveOptions::new().fix('C', 9));
+    assert_eq!(solution.unwrap().get('C'), Some(9));
+    let (solution, _) = cache.solve(&puzzle, &SolveOptions::new().fix('C', 8));
+    assert_eq!(solution.unwrap().get('C'), Some(8));
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+
+    // Letters of the options are renamed with the puzzle, letters missing in it are ignored
+    let renamed = Puzzle::parse("X + Y == Z").unwrap();
+    let (solution, _) = cache.solve(&renamed, &SolveOptions::new().fix('Z', 9).fix('Q', 1));
+    assert_eq!(solution.unwrap().get('Z'), Some(9));
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+}
+
+#[test]
+fn letter_order_gives_same_solution_as_solver() {
+    let mut cache = cached_bt();
+    let options = SolveOptions::new().letter_order(LetterOrder::Alphabetical);
+    for input in ["Z + Y == X", "SEND + MORE == MONEY", "A + A == B"] {
+        let puzzle = Puzzle::parse(input).unwrap();
+        assert_eq!(cache.solve(&puzzle, &options).0, crate::solver_bt::solve_with(&puzzle, &options), "{}", input);
+    }
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (0, 3, 0));
+}
+
+#[test]
+fn least_recently_used_puzzles_are_dropped() {
+    let mut cache = cached_bt().capacity(2);
+    let puzzles = ["A + B == C", "AB + C == DE", "A + BC == DEF"].map(|input| Puzzle::parse(input).unwrap());
+    let options = SolveOptions::default();
+    cache.solve(&puzzles[0], &options);
+    cache.solve(&puzzles[1], &options);
+    cache.solve(&puzzles[0], &options);
+    cache.solve(&puzzles[2], &options);
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (1, 3, 2));
+
+    cache.solve(&puzzles[0], &options);
+    cache.solve(&puzzles[1], &options);
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (2, 4, 2));
+
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.capacity(0).solve(&puzzles[0], &options).0.map(|solution| solution.len()), Some(3));
+}
+
+#[test]
+fn cache_kept_in_a_file() {
+    let path = std::env::temp_dir().join(format!("solver_cache_{}.txt", std::process::id()));
+    let puzzles = ["SEND + MORE == MONEY", "A + B == CDE", "I + BB == ILL"].map(|input| Puzzle::parse(input).unwrap());
+    let options = SolveOptions::default();
+
+    let mut cache = cached_bt().file(&path).unwrap();
+    let answers: Vec<_> = puzzles.iter().map(|puzzle| cache.solve(puzzle, &options)).collect();
+    cache.save().unwrap();
+
+    // Answers come from the file, only the two most recently used puzzles fit
+    let mut cache = SolverCache::new(|_: &Puzzle, _: &SolveOptions| panic!("solved again")).capacity(2).file(&path).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.solve(&puzzles[1], &options), answers[1]);
+    assert_eq!(cache.solve(&puzzles[2], &options), answers[2]);
+    assert_eq!((cache.hits(), cache.misses()), (2, 0));
+
+    fs::write(&path, "not a cache line\n").unwrap();
+    assert!(cached_bt().file(&path).unwrap().is_empty());
+    fs::remove_file(&path).unwrap();
+}
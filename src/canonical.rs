@@ -0,0 +1,301 @@
+use std::collections::{BTreeMap, HashMap};
+use itertools::Itertools;
+
+use crate::puzzle::{literal_digit, Equation, Puzzle, Term};
+
+/// Literal digits are encoded above any rank or label.
+const DIGIT_CODE: u32 = 100;
+
+/// Ties kept while arranging. Letters of the same rank which can be swapped without changing the puzzle,
+/// like the addends of `A + B + C == D`, tie in every order and all orders reach the same code, so any of them will do.
+/// Only letters of the same refined rank which cannot be swapped could be told apart by ties past the cap,
+/// for those the canonical form is best-effort and may depend on the order of the input.
+const MAX_TIES: usize = 1024;
+
+/// Letters labelled so far, label of a letter is its index.
+type Labels = Vec<char>;
+
+/// Codes of an arrangement with the labels it leaves, code of every part starts with its length so no code is
+/// a prefix of another one and the smallest code of the whole puzzle is made of the smallest codes of its parts.
+type Encoded = Vec<(Vec<u32>, Labels)>;
+
+/// Letter of a label in canonical puzzles, uppercase for the first 26 labels and lowercase for the rest.
+fn canonical_char(label: usize) -> char {
+    match label {
+        0..=25 => (b'A' + label as u8) as char,
+        _ => (b'a' + (label - 26) as u8) as char,
+    }
+}
+
+/// Rank of every letter by where it appears, the same for a letter under any name and in any order of terms.
+/// Ranks are refined by the ranks of letters sharing words, terms and equations with the letter until no more letters
+/// are told apart, so letters still tying can nearly always be swapped without changing the puzzle.
+fn letter_ranks(puzzle: &Puzzle) -> HashMap<char, u32> {
+    let mut ranks: HashMap<char, u32> = puzzle.equations.iter()
+        .flat_map(|equation| equation.words())
+        .flat_map(|word| word.chars())
+        .filter(|ch| literal_digit(*ch).is_none())
+        .map(|ch| (ch, 0))
+        .collect();
+    loop {
+        let refined = refine_ranks(puzzle, &ranks);
+        if refined.values().unique().count() == ranks.values().unique().count() {
+            return refined;
+        }
+        ranks = refined;
+    }
+}
+
+/// Ranks telling apart letters of the same rank whose places differ, in ranks of letters around them too.
+fn refine_ranks(puzzle: &Puzzle, ranks: &HashMap<char, u32>) -> HashMap<char, u32> {
+    let word_code = |word: &String| word.chars()
+        .map(|ch| literal_digit(ch).map_or_else(|| ranks[&ch], |digit| DIGIT_CODE + digit as u32))
+        .collect::<Vec<_>>();
+    let term_code = |term: &Term| (term.negative, term.factors.iter().map(word_code).sorted().collect::<Vec<_>>());
+    let side_code = |side: &[Term]| side.iter().map(term_code).sorted().collect::<Vec<_>>();
+    // Sides are numbered, so places do not repeat whole sides of long sums
+    let sides: Vec<_> = puzzle.equations.iter()
+        .flat_map(|equation| [side_code(&equation.left), side_code(&equation.right)])
+        .sorted()
+        .dedup()
+        .collect();
+    let side_id = |side: &[Term]| sides.binary_search(&side_code(side)).unwrap();
+
+    let mut places: HashMap<char, Vec<_>> = HashMap::new();
+    for equation in puzzle.equations.iter() {
+        let (left, right) = (side_id(&equation.left), side_id(&equation.right));
+        for (side, side_ids) in [(&equation.left, (left, right)), (&equation.right, (right, left))] {
+            for term in side.iter() {
+                let term_code = term_code(term);
+                for word in term.factors.iter() {
+                    let (len, code) = (word.chars().count(), word_code(word));
+                    for (idx, ch) in word.chars().enumerate().filter(|(_, ch)| literal_digit(*ch).is_none()) {
+                        places.entry(ch).or_default().push((len - idx, code.clone(), term_code.clone(), side_ids));
+                    }
+                }
+            }
+        }
+    }
+    let signatures: HashMap<char, _> = places.into_iter()
+        .map(|(ch, letter_places)| (ch, (ranks[&ch], letter_places.into_iter().sorted().collect::<Vec<_>>())))
+        .collect();
+    let ranked: Vec<_> = signatures.values().sorted().dedup().collect();
+    signatures.iter().map(|(&ch, signature)| (ch, ranked.binary_search(&signature).unwrap() as u32)).collect()
+}
+
+/// Smallest code of the items in any order, for every way of labelling which reaches it.
+fn arrange<T: PartialEq>(items: &[T], labels: &Labels, encode: impl Fn(&T, &Labels) -> Encoded) -> Encoded {
+    // Of equal items only the first one left is tried
+    let first_equal: Vec<usize> = items.iter().map(|item| items.iter().position(|other| other == item).unwrap()).collect();
+    let mut arranged = vec![(vec![items.len() as u32], labels.clone(), vec![false; items.len()])];
+    for _ in 0..items.len() {
+        let mut next = vec![];
+        for (code, labels, used) in arranged.iter() {
+            let mut tried = vec![false; items.len()];
+            let candidates = (0..items.len()).filter(|&idx| !used[idx] && !std::mem::replace(&mut tried[first_equal[idx]], true));
+            for idx in candidates {
+                for (item_code, labels) in encode(&items[idx], labels) {
+                    let mut used = used.clone();
+                    used[idx] = true;
+                    next.push(([code.as_slice(), &item_code].concat(), labels, used));
+                }
+            }
+        }
+        let smallest = next.iter().map(|(code, _, _)| code.clone()).min().unwrap_or_default();
+        arranged = next.into_iter()
+            .filter(|(code, _, _)| *code == smallest)
+            .unique_by(|(_, labels, used)| (labels.clone(), used.clone()))
+            .take(MAX_TIES)
+            .collect();
+    }
+    arranged.into_iter().map(|(code, labels, _)| (code, labels)).unique_by(|(_, labels)| labels.clone()).collect()
+}
+
+/// Parts of the puzzle encoded with letters replaced by their rank and label.
+struct Encoder {
+    ranks: HashMap<char, u32>,
+}
+
+impl Encoder {
+    /// Letters seen for the first time get the next labels.
+    fn word(&self, word: &str, labels: &Labels) -> Encoded {
+        let mut labels = labels.clone();
+        let mut code = vec![word.chars().count() as u32];
+        for ch in word.chars() {
+            match literal_digit(ch) {
+                Some(digit) => code.push(DIGIT_CODE + digit as u32),
+                None => {
+                    let label = labels.iter().position(|&labelled| labelled == ch).unwrap_or_else(|| {
+                        labels.push(ch);
+                        labels.len() - 1
+                    });
+                    code.extend([self.ranks[&ch], label as u32]);
+                }
+            }
+        }
+        vec![(code, labels)]
+    }
+
+    fn term(&self, term: &Term, labels: &Labels) -> Encoded {
+        arrange(&term.factors, labels, |word, labels| self.word(word, labels))
+            .into_iter()
+            .map(|(code, labels)| ([&[term.negative as u32][..], &code].concat(), labels))
+            .collect()
+    }
+
+    /// Side with fewer terms goes first and is written on the right, like the result of a sum.
+    fn equation(&self, equation: &Equation, labels: &Labels) -> Encoded {
+        let side = |terms: &[Term], labels: &Labels| arrange(terms, labels, |term, labels| self.term(term, labels));
+        let encoded: Encoded = [(&equation.right, &equation.left), (&equation.left, &equation.right)].into_iter()
+            .flat_map(|(first, second)| side(first, labels).into_iter()
+                .flat_map(move |(first_code, labels)| side(second, &labels).into_iter()
+                    .map(move |(second_code, labels)| ([first_code.as_slice(), &second_code].concat(), labels))))
+            .collect();
+        let smallest = encoded.iter().map(|(code, _)| code.clone()).min().unwrap_or_default();
+        encoded.into_iter().filter(|(code, _)| *code == smallest).unique_by(|(_, labels)| labels.clone()).collect()
+    }
+}
+
+/// Reads back parts of the code in the order `Encoder` wrote them.
+struct Decoder<'a> {
+    code: &'a [u32],
+}
+
+impl Decoder<'_> {
+    fn next(&mut self) -> u32 {
+        let (&value, rest) = self.code.split_first().unwrap();
+        self.code = rest;
+        value
+    }
+
+    fn word(&mut self) -> String {
+        (0..self.next())
+            .map(|_| match self.next() {
+                code if code >= DIGIT_CODE => char::from_digit(code - DIGIT_CODE, 10).unwrap(),
+                _rank => canonical_char(self.next() as usize),
+            })
+            .collect()
+    }
+
+    fn side(&mut self) -> Vec<Term> {
+        (0..self.next())
+            .map(|_| {
+                let negative = self.next() == 1;
+                Term { negative, factors: (0..self.next()).map(|_| self.word()).collect() }
+            })
+            .collect()
+    }
+}
+
+impl Puzzle {
+    /// Same problem written in a single way shared by all puzzles differing only in names of letters, order of equations,
+    /// of terms and of factors, and in which side of an equation is which. Letters are renamed to `A`, `B`, ... and the
+    /// shorter side is written on the right, e.g. `SEND + MORE == MONEY` becomes `FDCG + ABHD == ABCDE`.
+    pub fn canonical(&self) -> Puzzle {
+        self.canonical_letters().0
+    }
+
+    /// Same as `canonical` with the canonical letter of every letter of the puzzle,
+    /// so solutions can be moved between isomorphic puzzles.
+    pub fn canonical_letters(&self) -> (Puzzle, BTreeMap<char, char>) {
+        let encoder = Encoder { ranks: letter_ranks(self) };
+        let (code, labels) = arrange(&self.equations, &vec![], |equation, labels| encoder.equation(equation, labels)).swap_remove(0);
+
+        let mut decoder = Decoder { code: &code };
+        let equations: Vec<Equation> = (0..decoder.next())
+            .map(|_| {
+                let right = decoder.side();
+                Equation { left: decoder.side(), right }
+            })
+            .collect();
+        let unique_chars = equations.iter()
+            .flat_map(|equation| equation.words())
+            .flat_map(|word| word.chars())
+            .filter(|ch| ch.is_alphabetic())
+            .unique()
+            .collect();
+        let letters = labels.into_iter().enumerate().map(|(label, ch)| (ch, canonical_char(label))).collect();
+        (Puzzle { unique_chars, equations, base: self.base }, letters)
+    }
+
+    /// Whether the puzzles are the same problem, see `canonical`.
+    pub fn is_isomorphic(&self, other: &Puzzle) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
+
+#[test]
+fn renamed_and_reordered_puzzles_are_isomorphic() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    for other in ["MORE + SEND == MONEY", "MONEY == SEND + MORE", "TFOE + NPSF == NPOFZ"] {
+        assert!(puzzle.is_isomorphic(&Puzzle::parse(other).unwrap()), "{}", other);
+    }
+    for other in ["SEND + MORE == MONEE", "SEND - MORE == MONEY", "SEND * MORE == MONEY", "SEND + MORE + M == MONEY"] {
+        assert!(!puzzle.is_isomorphic(&Puzzle::parse(other).unwrap()), "{}", other);
+    }
+
+    // Letters swapped only in one equation are told apart by the other one
+    let puzzle = Puzzle::parse("A + B + C + D + E + F + G + H == J, A + J + I == BC").unwrap();
+    assert!(puzzle.is_isomorphic(&Puzzle::parse("H + B + C + D + E + F + G + A == J, H + J + I == GF").unwrap()));
+    assert!(!puzzle.is_isomorphic(&Puzzle::parse("A + B + C + D + E + F + G + H == J, A + J + I == BA").unwrap()));
+
+    let base_8 = Puzzle::parse_with("SEND + MORE == MONEY", &crate::puzzle::ParseOptions::new().base(8)).unwrap();
+    assert!(!puzzle.is_isomorphic(&base_8));
+}
+
+#[test]
+fn canonical_form() {
+    let canonical = Puzzle::parse("MORE + SEND == MONEY").unwrap().canonical();
+    assert_eq!(canonical.to_string(), "FDCG + ABHD == ABCDE");
+    assert_eq!(Puzzle::parse(&canonical.to_string()).unwrap(), canonical);
+    assert_eq!(canonical.canonical(), canonical);
+
+    let puzzle = Puzzle::parse("TO * TO == OUT, B + A + A == C").unwrap();
+    assert_eq!(puzzle.canonical().to_string(), "B + C + C == A, FD * FD == DEF");
+    assert!(puzzle.is_isomorphic(&Puzzle::parse("Z == Y + X + Y, PQ * PQ == QRP").unwrap()));
+}
+
+#[test]
+fn canonical_letters_carry_solutions_over() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let other = Puzzle::parse("TFOE + NPSF == NPOFZ").unwrap();
+    let (canonical, letters) = puzzle.canonical_letters();
+    let (_, other_letters) = other.canonical_letters();
+    assert_eq!(canonical, other.canonical());
+
+    // Solution of one puzzle mapped through the canonical letters solves the other one
+    let solution = crate::solver_bt::solve_puzzle(&puzzle).unwrap();
+    let to_other: BTreeMap<char, char> = other_letters.iter().map(|(&ch, &canonical)| (canonical, ch)).collect();
+    let moved: crate::solution::Solution = solution.iter().map(|(ch, digit)| (to_other[&letters[&ch]], digit)).collect();
+    assert!(moved.verify(&other));
+}
+
+#[test]
+fn literal_digits_and_many_letters() {
+    let options = crate::puzzle::ParseOptions::new().literal_digits(true);
+    let puzzle = Puzzle::parse_with("I + B9 == 1LL", &options).unwrap();
+    assert_eq!(puzzle.canonical().to_string(), "B + C9 == 1AA");
+    assert!(!puzzle.is_isomorphic(&Puzzle::parse_with("I + B8 == 1LL", &options).unwrap()));
+
+    // Letters past the 26th are renamed to lowercase ones
+    let letters: Vec<char> = "QWERTYUIOPASDFGHJKLZXCVBNMÄÖÜÉÈÀÇÑÍÓ".chars().collect();
+    let input = format!("{} == {}", letters[..18].iter().collect::<String>(), letters[18..].iter().collect::<String>());
+    let canonical = Puzzle::parse_with(&input, &crate::puzzle::ParseOptions::new().base(36)).unwrap().canonical();
+    assert_eq!(canonical.to_string(), "STUVWXYZabcdefghij == ABCDEFGHIJKLMNOPQR");
+    assert_eq!(Puzzle::parse_with(&canonical.to_string(), &crate::puzzle::ParseOptions::new().base(36)).unwrap(), canonical);
+}
+
+#[test]
+fn ties_past_the_cap() {
+    // Eight addends can be swapped in 40320 orders, far more than the ties kept, any of them gives the same form
+    let puzzle = Puzzle::parse("A + B + C + D + E + F + G + H == IJ").unwrap();
+    let other = Puzzle::parse("Q + P + O + N + M + L + K + J == ZY").unwrap();
+    assert!(puzzle.is_isomorphic(&other));
+    assert_eq!(puzzle.canonical().to_string(), "C + D + E + F + G + H + I + J == AB");
+
+    // Letters of the same places are told apart by letters they share words with
+    let puzzle = Puzzle::parse("AB + CD == EF, A + E == G, C + F == H").unwrap();
+    let other = Puzzle::parse("CD + AB == EF, C + E == G, A + F == H").unwrap();
+    assert!(puzzle.is_isomorphic(&other));
+    assert!(!puzzle.is_isomorphic(&Puzzle::parse("AB + CD == EF, A + E == G, A + F == H").unwrap()));
+}
@@ -0,0 +1,242 @@
+use std::{collections::HashSet, ops::RangeInclusive};
+use itertools::Itertools;
+
+use crate::options::SolveOptions;
+use crate::puzzle::{ParseOptions, Puzzle};
+use crate::solution::Solution;
+use crate::solver_bt;
+
+/// Shape of generated puzzles, a sum of `addends` words equal to a result word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerateOptions {
+    addends: usize,
+    word_lengths: RangeInclusive<usize>,
+    letters: RangeInclusive<usize>,
+    base: u8,
+    seed: u64,
+    max_attempts: usize,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            addends: 2,
+            word_lengths: 2..=10,
+            letters: 1..=10,
+            base: 10,
+            seed: 0,
+            max_attempts: 10_000,
+        }
+    }
+}
+
+impl GenerateOptions {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn addends(mut self, addends: usize) -> Self {
+        self.addends = addends;
+        self
+    }
+
+    /// Lengths of the addends and of the result, words of other lengths in the list are skipped.
+    pub fn word_lengths(mut self, word_lengths: RangeInclusive<usize>) -> Self {
+        self.word_lengths = word_lengths;
+        self
+    }
+
+    /// Count of distinct letters of the whole puzzle, never more than the base.
+    pub fn letters(mut self, letters: RangeInclusive<usize>) -> Self {
+        self.letters = letters;
+        self
+    }
+
+    /// Base of the puzzles, 2..=36 like `ParseOptions::base`, nothing is generated in other bases.
+    pub fn base(mut self, base: u8) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Same seed, word list and options always generate the same puzzles.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Combinations of words tried before giving up.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+}
+
+/// Puzzle with exactly one solution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    /// Puzzle as written, e.g. `SEND + MORE == MONEY`.
+    pub text: String,
+    pub puzzle: Puzzle,
+    pub solution: Solution,
+}
+
+/// SplitMix64, enough for picking words and small enough to keep results stable across releases.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Words of the list usable in a puzzle - uppercased, made of letters only and of allowed length, without duplicates.
+fn candidate_words<S: AsRef<str>>(words: &[S], options: &GenerateOptions) -> Vec<String> {
+    words.iter()
+        .map(|word| word.as_ref().trim().to_uppercase())
+        .filter(|word| !word.is_empty() && word.chars().all(|ch| ch.is_alphabetic()))
+        .filter(|word| options.word_lengths.contains(&word.chars().count()))
+        .unique()
+        .collect()
+}
+
+/// Whether the addends can sum up to a number as long as the result, leaving the rest to the solver.
+fn plausible_lengths(addends: &[&String], result: &str, base: u8) -> bool {
+    let longest = addends.iter().map(|word| word.chars().count()).max().unwrap_or(0);
+    // Sum of n words of `longest` digits has at most as many more digits as n - 1 has
+    let extra = std::iter::successors(Some(addends.len() - 1), |&n| Some(n / base as usize)).take_while(|&n| n > 0).count();
+    (longest..=longest + extra).contains(&result.chars().count())
+}
+
+/// Whether no solution other than the given one exists, every other solution gives some letter another digit.
+fn is_unique(puzzle: &Puzzle, solution: &Solution) -> bool {
+    solution.iter().all(|(letter, digit)| {
+        let options = SolveOptions::new().domain(letter, (0..puzzle.base).filter(|&other| other != digit));
+        solver_bt::solve_with(puzzle, &options).is_none()
+    })
+}
+
+/// Puzzle of distinct words picked at random, the last one is the result.
+fn pick_words<'a>(words: &'a [String], options: &GenerateOptions, rng: &mut Rng) -> Vec<&'a String> {
+    let mut picked: Vec<&String> = vec![];
+    while picked.len() <= options.addends {
+        let word = &words[rng.below(words.len())];
+        if !picked.contains(&word) {
+            picked.push(word);
+        }
+    }
+    picked
+}
+
+fn attempt(picked: &[&String], options: &GenerateOptions) -> Option<Generated> {
+    let (result, addends) = picked.split_last()?;
+    if !plausible_lengths(addends, result, options.base) {
+        return None;
+    }
+    let letters = picked.iter().flat_map(|word| word.chars()).unique().count();
+    if !options.letters.contains(&letters) || letters > options.base as usize {
+        return None;
+    }
+
+    let text = format!("{} == {}", addends.iter().join(" + "), result);
+    let puzzle = Puzzle::parse_with(&text, &ParseOptions::new().base(options.base)).ok()?;
+    let solution = solver_bt::solve_puzzle(&puzzle)?;
+    is_unique(&puzzle, &solution).then_some(Generated { text, puzzle, solution })
+}
+
+/// Up to `count` different puzzles made of words of the list, each with exactly one solution.
+/// Fewer are returned when `max_attempts` combinations of words did not give enough.
+pub fn generate_many<S: AsRef<str>>(words: &[S], options: &GenerateOptions, count: usize) -> Vec<Generated> {
+    let words = candidate_words(words, options);
+    let mut generated: Vec<Generated> = vec![];
+    if !(2..=36).contains(&options.base) || options.addends == 0 || words.len() <= options.addends {
+        return generated;
+    }
+
+    let mut rng = Rng(options.seed);
+    let mut tried = HashSet::new();
+    for _ in 0..options.max_attempts {
+        if generated.len() == count {
+            break;
+        }
+        let picked = pick_words(&words, options, &mut rng);
+        // Addends in another order make the same puzzle, each is solved once
+        let (result, addends) = picked.split_last().unwrap();
+        if !tried.insert((addends.iter().sorted().join(" + "), result.to_string())) {
+            continue;
+        }
+        generated.extend(attempt(&picked, options));
+    }
+    generated
+}
+
+/// Puzzle made of words of the list with exactly one solution, see `generate_many`.
+pub fn generate<S: AsRef<str>>(words: &[S], options: &GenerateOptions) -> Option<Generated> {
+    generate_many(words, options, 1).pop()
+}
+
+#[cfg(test)]
+const WORDS: [&str; 16] = ["at", "eat", "ate", "tea", "sea", "tan", "ant", "net", "ten", "rat", "art", "tar", "east", "rest", "star", "arts"];
+
+#[cfg(test)]
+fn texts(generated: &[Generated]) -> Vec<&str> {
+    generated.iter().map(|puzzle| puzzle.text.as_str()).collect()
+}
+
+#[cfg(test)]
+fn assert_single_solution(puzzle: &Generated) {
+    assert_eq!(crate::solver_bf::solve_all(&puzzle.puzzle, &SolveOptions::default()), vec![puzzle.solution.clone()], "{}", puzzle.text);
+}
+
+#[test]
+fn generated_puzzles_have_one_solution() {
+    let generated = generate_many(&WORDS, &GenerateOptions::new().letters(1..=6), 4);
+    assert_eq!(generated.len(), 4);
+    for puzzle in generated.iter() {
+        assert_single_solution(puzzle);
+        assert!(puzzle.solution.len() <= 6);
+    }
+    assert!(texts(&generated).iter().all_unique());
+
+    let words = ["send", "more", "money", "east", "west", "three"];
+    let generated = generate_many(&words, &GenerateOptions::new(), 5);
+    assert_eq!(texts(&generated).into_iter().sorted().collect::<Vec<_>>(), ["MORE + SEND == MONEY", "WEST + EAST == THREE"]);
+}
+
+#[test]
+fn same_seed_same_puzzles() {
+    let options = GenerateOptions::new().letters(1..=6).max_attempts(1000);
+    let words = WORDS.iter().map(|word| word.to_uppercase()).chain(["it's".to_string(), "Tea".to_string()]).collect::<Vec<_>>();
+    assert_eq!(texts(&generate_many(&WORDS, &options, 3)), texts(&generate_many(&words, &options, 3)));
+    assert_ne!(texts(&generate_many(&WORDS, &options, 3)), texts(&generate_many(&WORDS, &options.seed(1), 3)));
+}
+
+#[test]
+fn respects_shape_options() {
+    let options = GenerateOptions::new().addends(3).word_lengths(3..=4).letters(5..=6).base(8).seed(2);
+    let generated = generate_many(&WORDS, &options, 2);
+    assert_eq!(generated.len(), 2);
+    for puzzle in generated.iter() {
+        assert_single_solution(puzzle);
+        assert_eq!(puzzle.text.matches(" + ").count(), 2);
+        assert!(puzzle.text.split([' ', '+', '=']).filter(|word| !word.is_empty()).all(|word| (3..=4).contains(&word.len())));
+        assert!((5..=6).contains(&puzzle.solution.len()));
+        assert!(puzzle.solution.values().all(|&digit| digit < 8));
+    }
+}
+
+#[test]
+fn impossible_options() {
+    assert_eq!(generate(&WORDS, &GenerateOptions::new().word_lengths(5..=9)), None);
+    assert_eq!(generate(&WORDS, &GenerateOptions::new().letters(9..=10)), None);
+    assert_eq!(generate(&WORDS, &GenerateOptions::new().addends(20)), None);
+    assert_eq!(generate(&["ten", "net", "tent"], &GenerateOptions::new()), None);
+    for base in [0, 1, 37] {
+        assert_eq!(generate_many(&WORDS, &GenerateOptions::new().base(base), 2), vec![], "base {}", base);
+    }
+}
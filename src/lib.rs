@@ -1,3 +1,22 @@
+pub mod bignum;
+pub mod cache;
+pub mod canonical;
+pub mod generator;
+pub mod limits;
 pub mod linkedlist;
+pub mod options;
+pub mod progress;
+pub mod puzzle;
+pub mod rating;
+pub mod solution;
 pub mod solver_bf;
 pub mod solver_bt;
+// Previous backtracking, only for benchmarks
+#[cfg(feature = "baseline")]
+pub mod solver_bt_baseline;
+pub mod solver_cp;
+pub mod solver_deduce;
+pub mod solver_lin;
+pub mod stats;
+pub mod trace;
+pub mod verify;
@@ -0,0 +1,135 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, time::{Duration, Instant}};
+
+use crate::progress::ProgressObserver;
+use crate::solution::Solution;
+use crate::stats::SolveStats;
+
+/// Flag shared between threads, stops a running solve once cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    Deadline,
+    NodeLimit,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveOutcome {
+    Solved(Solution),
+    /// Whole search space was searched without a solution.
+    NoSolution,
+    /// Search stopped by `Limits` before it could tell whether a solution exists.
+    Aborted { reason: AbortReason, partial_stats: SolveStats },
+}
+
+impl SolveOutcome {
+    pub fn solution(self) -> Option<Solution> {
+        match self {
+            SolveOutcome::Solved(solution) => Some(solution),
+            _ => None,
+        }
+    }
+}
+
+/// Bounds of a single solve and hooks watching it, nothing is bounded by default.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    cancel_token: Option<CancelToken>,
+    observer: Option<ProgressObserver>,
+}
+
+impl Limits {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Deadline counted from now.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// Nodes a solver may visit - digits drawn for letters, or permutations tried by `solver_bf`.
+    pub fn max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    pub fn cancel_token(mut self, cancel_token: CancelToken) -> Self {
+        self.cancel_token = Some(cancel_token);
+        self
+    }
+
+    pub fn progress(mut self, observer: ProgressObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    pub(crate) fn observer(&self) -> Option<ProgressObserver> {
+        self.observer.clone()
+    }
+
+    pub(crate) fn exceeds_nodes(&self, nodes: u64) -> bool {
+        self.max_nodes.is_some_and(|max_nodes| nodes > max_nodes)
+    }
+
+    /// Reason to stop the search, reading the clock only when a deadline is set.
+    pub(crate) fn check(&self, nodes: u64) -> Option<AbortReason> {
+        if self.cancel_token.as_ref().is_some_and(CancelToken::is_cancelled) {
+            Some(AbortReason::Cancelled)
+        } else if self.exceeds_nodes(nodes) {
+            Some(AbortReason::NodeLimit)
+        } else if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            Some(AbortReason::Deadline)
+        } else {
+            None
+        }
+    }
+
+    /// Same as `check`, but the token and the clock are read only every 1024 nodes, for loops too tight to read them on every node.
+    pub(crate) fn check_throttled(&self, nodes: u64) -> Option<AbortReason> {
+        if self.exceeds_nodes(nodes) {
+            Some(AbortReason::NodeLimit)
+        } else if nodes.is_multiple_of(1024) {
+            self.check(nodes)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn unbounded_limits_never_abort() {
+    assert_eq!(Limits::new().check(u64::MAX), None);
+}
+
+#[test]
+fn limits_abort_for_each_reason() {
+    assert_eq!(Limits::new().max_nodes(10).check(10), None);
+    assert_eq!(Limits::new().max_nodes(10).check(11), Some(AbortReason::NodeLimit));
+    assert_eq!(Limits::new().timeout(Duration::ZERO).check(0), Some(AbortReason::Deadline));
+
+    let token = CancelToken::new();
+    let limits = Limits::new().cancel_token(token.clone());
+    assert_eq!(limits.check(0), None);
+    token.cancel();
+    assert_eq!(limits.check(0), Some(AbortReason::Cancelled));
+}
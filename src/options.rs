@@ -0,0 +1,165 @@
+#![allow(unused)]
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Which words may start with zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LeadingZeros {
+    /// No word may start with zero, single letter words included.
+    #[default]
+    Strict,
+    /// Only single letter words may be zero.
+    SingleLetter,
+    /// Any word may start with zero.
+    Allowed,
+}
+
+impl LeadingZeros {
+    pub(crate) fn forbids_zero(self, word: &str) -> bool {
+        match self {
+            LeadingZeros::Strict => true,
+            LeadingZeros::SingleLetter => word.chars().nth(1).is_some(),
+            LeadingZeros::Allowed => false,
+        }
+    }
+}
+
+/// Order in which solvers assign digits to letters, ties keep the order of first appearance.
+/// Solvers go through digits in ascending order, so together with the letter order it decides
+/// which solution is returned first for puzzles with many solutions. `solver_cp` and `solver_lin` pick
+/// letters by their own heuristics and follow the order only between letters the heuristic ties.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LetterOrder {
+    /// As letters first appear in the puzzle.
+    #[default]
+    FirstAppearance,
+    Alphabetical,
+    /// Letters at the highest position of any word first.
+    MostSignificantFirst,
+    /// Letters appearing most times first.
+    MostFrequentFirst,
+}
+
+/// Which letter of the current column `solver_bt` draws a digit for next.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Heuristic {
+    /// Letters in `LetterOrder`.
+    #[default]
+    Static,
+    /// Letter with the fewest digits left first (MRV). Only letters of the current column are ranked, by digits their
+    /// domains leave after those taken by letters mapped so far.
+    MinimumRemainingValues,
+    /// Letters starting any word first, they cannot take zero.
+    LeadingFirst,
+    /// Letters with the highest absolute coefficient in the column first.
+    LargestCoefficient,
+}
+
+/// Hints narrowing the search, shared by all solvers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SolveOptions {
+    domains: BTreeMap<char, BTreeSet<u8>>,
+    pub(crate) leading_zeros: LeadingZeros,
+    pub(crate) letter_order: LetterOrder,
+    pub(crate) heuristic: Heuristic,
+}
+
+impl SolveOptions {
+    pub fn new() -> Self { Self::default() }
+
+    /// Bind a letter to a digit, e.g. "E = 5".
+    pub fn fix(self, letter: char, digit: u8) -> Self {
+        self.domain(letter, [digit])
+    }
+
+    /// Restrict a letter to given digits, e.g. "M in {1, 2}" or "S is odd".
+    /// Restrictions of the same letter are intersected, letters missing in the puzzle are ignored.
+    pub fn domain(mut self, letter: char, digits: impl IntoIterator<Item = u8>) -> Self {
+        let digits: BTreeSet<u8> = digits.into_iter().collect();
+        self.domains.entry(letter)
+            .and_modify(|domain| domain.retain(|digit| digits.contains(digit)))
+            .or_insert(digits);
+        self
+    }
+
+    pub fn leading_zeros(mut self, leading_zeros: LeadingZeros) -> Self {
+        self.leading_zeros = leading_zeros;
+        self
+    }
+
+    pub fn letter_order(mut self, letter_order: LetterOrder) -> Self {
+        self.letter_order = letter_order;
+        self
+    }
+
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    pub(crate) fn allows(&self, letter: char, digit: u8) -> bool {
+        self.domains.get(&letter).is_none_or(|domain| domain.contains(&digit))
+    }
+
+    /// Single digit of the base the letter is restricted to, digits out of the base are not counted.
+    pub(crate) fn fixed_digit(&self, letter: char, base: u8) -> Option<u8> {
+        self.domains.get(&letter)
+            .map(|domain| domain.range(..base).copied().collect::<Vec<_>>())
+            .filter(|digits| digits.len() == 1)
+            .map(|digits| digits[0])
+    }
+
+    pub(crate) fn forbids_leading_zero(&self, word: &str) -> bool {
+        self.leading_zeros.forbids_zero(word)
+    }
+
+    /// Same options for the puzzle with letters renamed, letters missing in `letters` are dropped.
+    pub(crate) fn renamed(&self, letters: &BTreeMap<char, char>) -> Self {
+        let domains = self.domains.iter()
+            .filter_map(|(letter, domain)| Some((*letters.get(letter)?, domain.clone())))
+            .collect();
+        Self { domains, ..self.clone() }
+    }
+}
+
+#[test]
+fn unrestricted_letter_allows_every_digit() {
+    let options = SolveOptions::new().fix('E', 5);
+    assert!((0..10).all(|digit| options.allows('S', digit)));
+    assert_eq!(options.fixed_digit('S', 10), None);
+}
+
+#[test]
+fn fixed_letter_allows_single_digit() {
+    let options = SolveOptions::new().fix('E', 5);
+    assert!(options.allows('E', 5));
+    assert!(!options.allows('E', 4));
+    assert_eq!(options.fixed_digit('E', 10), Some(5));
+}
+
+#[test]
+fn domains_of_same_letter_are_intersected() {
+    let options = SolveOptions::new()
+        .domain('S', [1, 3, 5, 7, 9])
+        .domain('S', 5..10);
+    let allowed: Vec<_> = (0..10).filter(|&digit| options.allows('S', digit)).collect();
+    assert_eq!(allowed, vec![5, 7, 9]);
+
+    assert_eq!(options.fixed_digit('S', 6), Some(5));
+    let options = options.domain('S', [7]);
+    assert_eq!(options.fixed_digit('S', 10), Some(7));
+    assert_eq!(options.fixed_digit('S', 7), None);
+}
+
+#[test]
+fn leading_zeros_policies() {
+    assert!(SolveOptions::new().forbids_leading_zero("A"));
+    assert!(SolveOptions::new().forbids_leading_zero("AB"));
+
+    let options = SolveOptions::new().leading_zeros(LeadingZeros::SingleLetter);
+    assert!(!options.forbids_leading_zero("Ż"));
+    assert!(options.forbids_leading_zero("ŻB"));
+
+    let options = SolveOptions::new().leading_zeros(LeadingZeros::Allowed);
+    assert!(!options.forbids_leading_zero("A"));
+    assert!(!options.forbids_leading_zero("AB"));
+}
@@ -0,0 +1,74 @@
+use std::{fmt, sync::Arc, time::{Duration, Instant}};
+
+/// Snapshot of a running search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Estimated fraction of the search space explored, from 0.0 to 1.0.
+    pub explored: f64,
+    pub nodes: u64,
+    pub nodes_per_second: f64,
+    pub solutions: usize,
+    pub elapsed: Duration,
+}
+
+/// Callback receiving `Progress` at most once per interval, and once more when the search ends.
+#[derive(Clone)]
+pub struct ProgressObserver {
+    interval: Duration,
+    callback: Arc<dyn Fn(&Progress) + Send + Sync>,
+}
+
+impl ProgressObserver {
+    pub fn new(interval: Duration, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        Self { interval, callback: Arc::new(callback) }
+    }
+}
+
+impl fmt::Debug for ProgressObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressObserver").field("interval", &self.interval).finish_non_exhaustive()
+    }
+}
+
+/// Calls the observer, if there is any, once its interval passes.
+#[derive(Debug)]
+pub(crate) struct ProgressReporter {
+    observer: Option<ProgressObserver>,
+    start: Instant,
+    last_report: Instant,
+}
+
+impl ProgressReporter {
+    pub(crate) fn new(observer: Option<ProgressObserver>) -> Self {
+        let now = Instant::now();
+        Self { observer, start: now, last_report: now }
+    }
+
+    /// Reads the clock only every 1024 nodes, `explored` is evaluated only when reporting.
+    pub(crate) fn tick(&mut self, nodes: u64, solutions: usize, explored: impl FnOnce() -> f64) {
+        let Some(observer) = &self.observer else {
+            return;
+        };
+        if !nodes.is_multiple_of(1024) {
+            return;
+        }
+        let now = Instant::now();
+        if now - self.last_report >= observer.interval {
+            self.last_report = now;
+            self.report(nodes, solutions, explored());
+        }
+    }
+
+    pub(crate) fn finish(&mut self, nodes: u64, solutions: usize, explored: f64) {
+        self.report(nodes, solutions, explored);
+    }
+
+    fn report(&self, nodes: u64, solutions: usize, explored: f64) {
+        let Some(observer) = &self.observer else {
+            return;
+        };
+        let elapsed = self.start.elapsed();
+        let nodes_per_second = if elapsed.is_zero() { 0.0 } else { nodes as f64 / elapsed.as_secs_f64() };
+        (observer.callback)(&Progress { explored: explored.clamp(0.0, 1.0), nodes, nodes_per_second, solutions, elapsed });
+    }
+}
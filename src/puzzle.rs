@@ -0,0 +1,391 @@
+#![allow(unused)]
+use std::{cmp::Reverse, collections::HashMap, fmt, str::FromStr};
+use itertools::Itertools;
+
+use crate::options::LetterOrder;
+
+/// One addend of a side - a single word or a product of words, optionally subtracted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Term {
+    pub(crate) negative: bool,
+    pub(crate) factors: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Equation {
+    pub(crate) left: Vec<Term>,
+    pub(crate) right: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    MissingEquality,
+    EmptyWord,
+    InvalidWord(String),
+    InvalidBase(u8),
+    InvalidDigit { digit: char, base: u8 },
+    TooManyLetters { letters: usize, base: u8 },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingEquality => write!(f, "puzzle has no '==' or '=' separating the sides"),
+            ParseError::EmptyWord => write!(f, "puzzle contains an empty word"),
+            ParseError::InvalidWord(word) => write!(f, "'{}' is not a valid word", word),
+            ParseError::InvalidBase(base) => write!(f, "base {} is outside of supported range 2..=36", base),
+            ParseError::InvalidDigit { digit, base } => write!(f, "digit '{}' is not valid in base {}", digit, base),
+            ParseError::TooManyLetters { letters, base } => write!(f, "{} distinct letters do not fit in base {}", letters, base),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Equation {
+    fn parse(input: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+        let input = input.trim();
+        let (left, right) = input.split_once("==")
+            .or_else(|| input.split_once('='))
+            .ok_or(ParseError::MissingEquality)?;
+
+        Ok(Self {
+            left: parse_side(left, options)?,
+            right: parse_side(right, options)?,
+        })
+    }
+
+    /// Terms of both sides, right side ones with the sign flipped so that all of them sum up to zero.
+    pub(crate) fn signed_terms(&self) -> impl Iterator<Item = (bool, &Term)> {
+        self.left.iter().map(|term| (term.negative, term))
+            .chain(self.right.iter().map(|term| (!term.negative, term)))
+    }
+
+    /// All words of both sides, factors of products included.
+    pub(crate) fn words(&self) -> impl Iterator<Item = &String> {
+        self.left.iter().chain(self.right.iter()).flat_map(|term| term.factors.iter())
+    }
+
+    /// Sums and differences can be checked column by column with carries, products need the whole value.
+    pub(crate) fn is_linear(&self) -> bool {
+        self.left.iter().chain(self.right.iter()).all(|term| term.factors.len() == 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    base: u8,
+    case_insensitive: bool,
+    literal_digits: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            base: 10,
+            case_insensitive: false,
+            literal_digits: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> Self { Self::default() }
+
+    /// Numeric base of the puzzle, 2..=36.
+    pub fn base(mut self, base: u8) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Treat lowercase and uppercase forms of a letter, ASCII or not, as the same letter.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Allow digits inside words, e.g. `"A1B + C == 2DE"`. Digits are fixed values and do not
+    /// stop letters from taking the same value.
+    pub fn literal_digits(mut self, literal_digits: bool) -> Self {
+        self.literal_digits = literal_digits;
+        self
+    }
+}
+
+/// Value of a digit written directly in a word, letters are never literal digits.
+pub(crate) fn literal_digit(ch: char) -> Option<u8> {
+    ch.to_digit(10).map(|digit| digit as u8)
+}
+
+/// Parsed alphametic, e.g. `"SEND + MORE == MONEY"` or `"TWO * TWO == SQUARE"`.
+/// Several equations sharing letters can be separated by commas or new lines.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Puzzle {
+    pub(crate) unique_chars: Vec<char>,
+    pub(crate) equations: Vec<Equation>,
+    pub(crate) base: u8,
+}
+
+impl Puzzle {
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        Self::parse_with(input, &ParseOptions::default())
+    }
+
+    pub fn parse_with(input: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+        if !(2..=36).contains(&options.base) {
+            return Err(ParseError::InvalidBase(options.base));
+        }
+
+        let input: String = if options.case_insensitive {
+            input.chars().map(to_single_uppercase).collect()
+        } else {
+            input.to_string()
+        };
+
+        let equations = input.split([',', '\n'])
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Equation::parse(line, options))
+            .collect::<Result<Vec<_>, _>>()?;
+        if equations.is_empty() {
+            return Err(ParseError::MissingEquality);
+        }
+
+        let unique_chars: Vec<char> = input.chars().filter(|ch| ch.is_alphabetic()).unique().collect();
+        if unique_chars.len() > options.base as usize {
+            return Err(ParseError::TooManyLetters { letters: unique_chars.len(), base: options.base });
+        }
+
+        Ok(Self {
+            unique_chars,
+            equations,
+            base: options.base,
+        })
+    }
+}
+
+impl Puzzle {
+    /// Letters of the puzzle in the order solvers should assign them.
+    pub(crate) fn ordered_chars(&self, order: LetterOrder) -> Vec<char> {
+        let words = || self.equations.iter().flat_map(|equation| equation.words());
+        let mut chars = self.unique_chars.clone();
+        match order {
+            LetterOrder::FirstAppearance => {}
+            LetterOrder::Alphabetical => chars.sort(),
+            LetterOrder::MostSignificantFirst => {
+                let mut significance: HashMap<char, usize> = HashMap::new();
+                for word in words() {
+                    let len = word.chars().count();
+                    for (idx, ch) in word.chars().enumerate() {
+                        let position = significance.entry(ch).or_default();
+                        *position = (*position).max(len - idx);
+                    }
+                }
+                chars.sort_by_key(|ch| Reverse(significance[ch]));
+            }
+            LetterOrder::MostFrequentFirst => {
+                let counts = words().flat_map(|word| word.chars()).counts();
+                chars.sort_by_key(|ch| Reverse(counts[ch]));
+            }
+        }
+        chars
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::parse(input)
+    }
+}
+
+/// Written as parsed, e.g. `SEND + MORE == MONEY`, equations separated by commas.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |terms: &[Term]| terms.iter()
+            .enumerate()
+            .map(|(idx, term)| {
+                let factors = term.factors.join(" * ");
+                // First term of a side is never negative, the grammar has no leading minus
+                match (idx, term.negative) {
+                    (0, _) => factors,
+                    (_, true) => format!(" - {}", factors),
+                    (_, false) => format!(" + {}", factors),
+                }
+            })
+            .collect::<String>();
+        write!(f, "{}", self.equations.iter().map(|equation| format!("{} == {}", side(&equation.left), side(&equation.right))).join(", "))
+    }
+}
+
+fn parse_side(input: &str, options: &ParseOptions) -> Result<Vec<Term>, ParseError> {
+    split_terms(input).into_iter()
+        .map(|(negative, term)| Ok(Term {
+            negative,
+            factors: term.split('*').map(|word| parse_word(word, options)).collect::<Result<_, _>>()?,
+        }))
+        .collect()
+}
+
+fn split_terms(input: &str) -> Vec<(bool, &str)> {
+    let mut terms = vec![];
+    let mut negative = false;
+    let mut rest = input;
+    while let Some(idx) = rest.find(['+', '-']) {
+        terms.push((negative, &rest[..idx]));
+        negative = rest[idx..].starts_with('-');
+        rest = &rest[idx + 1..];
+    }
+    terms.push((negative, rest));
+    terms
+}
+
+fn parse_word(input: &str, options: &ParseOptions) -> Result<String, ParseError> {
+    let word = input.trim();
+    if word.is_empty() {
+        return Err(ParseError::EmptyWord);
+    }
+    for ch in word.chars() {
+        match literal_digit(ch) {
+            Some(digit) if options.literal_digits && digit >= options.base => {
+                return Err(ParseError::InvalidDigit { digit: ch, base: options.base });
+            }
+            Some(_) if options.literal_digits => {}
+            _ if ch.is_alphabetic() => {}
+            _ => return Err(ParseError::InvalidWord(word.to_string())),
+        }
+    }
+    Ok(word.to_string())
+}
+
+fn to_single_uppercase(ch: char) -> char {
+    // Letters without a single char uppercase form, like 'ß', are kept as they are
+    let mut uppercase = ch.to_uppercase();
+    match (uppercase.next(), uppercase.next()) {
+        (Some(upper), None) => upper,
+        _ => ch,
+    }
+}
+
+#[cfg(test)]
+fn term(negative: bool, factors: &[&str]) -> Term {
+    Term { negative, factors: factors.iter().map(|s| s.to_string()).collect() }
+}
+
+#[test]
+fn parse_sum() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(puzzle.equations.len(), 1);
+    assert_eq!(puzzle.equations[0].left, vec![term(false, &["SEND"]), term(false, &["MORE"])]);
+    assert_eq!(puzzle.equations[0].right, vec![term(false, &["MONEY"])]);
+    assert_eq!(puzzle.unique_chars.len(), 8);
+    assert!(puzzle.equations[0].is_linear());
+}
+
+#[test]
+fn parse_product_and_difference() {
+    let puzzle = Puzzle::parse("AB * C - D == EF").unwrap();
+    assert_eq!(puzzle.equations[0].left, vec![term(false, &["AB", "C"]), term(true, &["D"])]);
+    assert!(!puzzle.equations[0].is_linear());
+}
+
+#[test]
+fn parse_words_on_both_sides() {
+    let puzzle = Puzzle::parse("A + B = C - D").unwrap();
+    assert_eq!(puzzle.equations[0].left, vec![term(false, &["A"]), term(false, &["B"])]);
+    assert_eq!(puzzle.equations[0].right, vec![term(false, &["C"]), term(true, &["D"])]);
+    let signs: Vec<_> = puzzle.equations[0].signed_terms().map(|(negative, _)| negative).collect();
+    assert_eq!(signs, vec![false, false, true, false]);
+}
+
+#[test]
+fn parse_system_of_equations() {
+    let puzzle = Puzzle::parse("AB + CD == EF, EF - GH == IJ\nA + B == C\n").unwrap();
+    assert_eq!(puzzle.equations.len(), 3);
+    assert_eq!(puzzle.equations[1].left, vec![term(false, &["EF"]), term(true, &["GH"])]);
+    assert_eq!(puzzle.equations[2].right, vec![term(false, &["C"])]);
+    assert_eq!(puzzle.unique_chars.len(), 10);
+}
+
+#[test]
+fn parse_rejects_malformed_input() {
+    assert_eq!(Puzzle::parse("A + B"), Err(ParseError::MissingEquality));
+    assert_eq!(Puzzle::parse(""), Err(ParseError::MissingEquality));
+    assert_eq!(Puzzle::parse("A + == B"), Err(ParseError::EmptyWord));
+    assert_eq!(Puzzle::parse("A / B == C"), Err(ParseError::InvalidWord("A / B".to_string())));
+    assert_eq!(Puzzle::parse("A = B = C"), Err(ParseError::InvalidWord("B = C".to_string())));
+}
+
+#[test]
+fn parse_in_other_base() {
+    let puzzle = Puzzle::parse_with("I + BB == ILL", &ParseOptions::new().base(16)).unwrap();
+    assert_eq!(puzzle.base, 16);
+    assert_eq!(Puzzle::parse("I + BB == ILL").unwrap().base, 10);
+}
+
+#[test]
+fn parse_rejects_invalid_base() {
+    assert_eq!(Puzzle::parse_with("A == B", &ParseOptions::new().base(1)), Err(ParseError::InvalidBase(1)));
+    assert_eq!(Puzzle::parse_with("A == B", &ParseOptions::new().base(37)), Err(ParseError::InvalidBase(37)));
+}
+
+#[test]
+fn parse_rejects_more_letters_than_base() {
+    let answer = Puzzle::parse_with("SEND + MORE == MONEY", &ParseOptions::new().base(7));
+    assert_eq!(answer, Err(ParseError::TooManyLetters { letters: 8, base: 7 }));
+    assert!(Puzzle::parse_with("SEND + MORE == MONEY", &ParseOptions::new().base(8)).is_ok());
+}
+
+#[test]
+fn parse_case_insensitive() {
+    let options = ParseOptions::new().case_insensitive(true);
+    let puzzle = Puzzle::parse_with("send + More == MONEY", &options).unwrap();
+    assert_eq!(puzzle.equations, Puzzle::parse("SEND + MORE == MONEY").unwrap().equations);
+    assert_eq!(Puzzle::parse("send + More == MONEY"), Err(ParseError::TooManyLetters { letters: 11, base: 10 }));
+}
+
+#[test]
+fn parse_non_ascii_letters() {
+    let puzzle = Puzzle::parse("ŻÓŁW + ŁĄKA == ŻABĘ").unwrap();
+    assert_eq!(puzzle.unique_chars.len(), 9);
+
+    let options = ParseOptions::new().case_insensitive(true);
+    let puzzle = Puzzle::parse_with("σενδ + μορε == ΜΟΝΕΥ", &options).unwrap();
+    assert_eq!(puzzle.equations[0].left, vec![term(false, &["ΣΕΝΔ"]), term(false, &["ΜΟΡΕ"])]);
+    assert_eq!(puzzle.unique_chars.len(), 8);
+}
+
+#[test]
+fn parse_literal_digits() {
+    let options = ParseOptions::new().literal_digits(true);
+    let puzzle = Puzzle::parse_with("A1B + C == 2DE", &options).unwrap();
+    assert_eq!(puzzle.equations[0].left, vec![term(false, &["A1B"]), term(false, &["C"])]);
+    assert_eq!(puzzle.unique_chars.len(), 5);
+
+    assert_eq!(Puzzle::parse("A1B + C == 2DE"), Err(ParseError::InvalidWord("A1B".to_string())));
+    let options = options.base(8);
+    assert_eq!(Puzzle::parse_with("A9 == B", &options), Err(ParseError::InvalidDigit { digit: '9', base: 8 }));
+}
+
+#[test]
+fn display_as_parsed() {
+    for input in ["SEND + MORE == MONEY", "TWO * TWO - A == SQUARE", "A + A == B, B + B == CD"] {
+        assert_eq!(Puzzle::parse(input).unwrap().to_string(), input);
+    }
+    assert_eq!(Puzzle::parse("A+B=C\nC=D+E").unwrap().to_string(), "A + B == C, C == D + E");
+}
+
+#[test]
+fn letters_in_first_appearance_order() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(puzzle.unique_chars, vec!['S', 'E', 'N', 'D', 'M', 'O', 'R', 'Y']);
+}
+
+#[test]
+fn letters_ordering() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(puzzle.ordered_chars(LetterOrder::FirstAppearance), puzzle.unique_chars);
+    assert_eq!(puzzle.ordered_chars(LetterOrder::Alphabetical), vec!['D', 'E', 'M', 'N', 'O', 'R', 'S', 'Y']);
+    assert_eq!(puzzle.ordered_chars(LetterOrder::MostSignificantFirst), vec!['M', 'S', 'O', 'E', 'N', 'R', 'D', 'Y']);
+    assert_eq!(puzzle.ordered_chars(LetterOrder::MostFrequentFirst), vec!['E', 'N', 'M', 'O', 'S', 'D', 'R', 'Y']);
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use crate::options::SolveOptions;
+use crate::puzzle::Puzzle;
+use crate::solver_bt;
+use crate::solver_deduce::{self, Rule};
+
+/// Level of a puzzle, from the lowest score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// Measured properties of a puzzle and the difficulty they add up to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    pub letters: usize,
+    /// Terms of all equations but the one of every right side, e.g. 2 for `SEND + MORE == MONEY`.
+    pub addends: usize,
+    /// Digits drawn by `solver_bt`.
+    pub nodes: u64,
+    /// Steps of the `solver_deduce` proof.
+    pub deduction_steps: usize,
+    /// Suppositions of the proof, steps no rule could make without trying digits.
+    pub guesses: usize,
+    /// Effective branching factor of `solver_bt`, digits drawn per letter so that `branching ^ letters == nodes`.
+    pub branching: f64,
+    /// Weighted sum of the properties, higher is harder, used to sort puzzles.
+    pub score: u32,
+    pub level: Level,
+}
+
+impl Level {
+    /// Levels split scores of well known puzzles, e.g. `TO + GO == OUT` is easy and `SEND + MORE == MONEY` medium.
+    pub fn from_score(score: u32) -> Self {
+        match score {
+            0..=59 => Level::Easy,
+            60..=99 => Level::Medium,
+            _ => Level::Hard,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Easy => "easy",
+            Level::Medium => "medium",
+            Level::Hard => "hard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Solves the puzzle with `solver_bt` and `solver_deduce` to measure how hard it is, unsolvable puzzles are rated too.
+pub fn rate(puzzle: &Puzzle) -> Rating {
+    let options = SolveOptions::default();
+    let (_, stats) = solver_bt::solve_with_stats(puzzle, &options);
+    let (_, proof) = solver_deduce::solve_explained(puzzle, &options);
+
+    let letters = puzzle.unique_chars.len();
+    let addends: usize = puzzle.equations.iter().map(|equation| equation.left.len() + equation.right.len() - 1).sum();
+    let guesses = proof.steps.iter().filter(|step| step.rule == Rule::Guess).count();
+    let branching = if letters == 0 { 0.0 } else { (stats.nodes.max(1) as f64).powf(1.0 / letters as f64) };
+    let score = 3.0 * letters as f64
+        + 2.0 * (addends.saturating_sub(1) as f64).min(10.0)
+        + 2.0 * (stats.nodes.max(1) as f64).log2()
+        + proof.steps.len() as f64 / 2.0
+        + 3.0 * (guesses as f64).min(10.0)
+        + 4.0 * (branching - 1.0).max(0.0);
+    let score = score.round() as u32;
+    Rating { letters, addends, nodes: stats.nodes, deduction_steps: proof.steps.len(), guesses, branching, score, level: Level::from_score(score) }
+}
+
+#[test]
+fn properties_of_send_more_money() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let rating = rate(&puzzle);
+    let (_, stats) = solver_bt::solve_with_stats(&puzzle, &SolveOptions::default());
+    let (_, proof) = solver_deduce::solve_explained(&puzzle, &SolveOptions::default());
+    assert_eq!((rating.letters, rating.addends, rating.nodes, rating.deduction_steps), (8, 2, stats.nodes, proof.steps.len()));
+    assert!(rating.guesses > 0);
+    assert!((rating.branching.powi(8) - stats.nodes as f64).abs() < 1.0);
+    assert_eq!(rating.level, Level::Medium);
+}
+
+#[test]
+fn levels_of_known_puzzles() {
+    let level = |text: &str| rate(&Puzzle::parse(text).unwrap()).level;
+    assert_eq!(level("I + BB == ILL"), Level::Easy);
+    assert_eq!(level("TO + GO == OUT"), Level::Easy);
+    assert_eq!(level("DONALD + GERALD == ROBERT"), Level::Medium);
+    assert_eq!(level("AND + A + STRONG + OFFENSE + AS + A + GOOD == DEFENSE"), Level::Hard);
+    assert_eq!(Level::from_score(0), Level::Easy);
+    assert!(Level::Easy < Level::Hard);
+    assert_eq!(Level::Medium.to_string(), "medium");
+}
+
+#[test]
+fn sort_puzzles_by_difficulty() {
+    let mut puzzles = ["SEND + MORE == MONEY", "A + A == B, B + B == CD, D + D == E", "A + A == B", "WEST + EAST == THREE"];
+    puzzles.sort_by_key(|text| rate(&Puzzle::parse(text).unwrap()).score);
+    assert_eq!(puzzles, ["A + A == B", "WEST + EAST == THREE", "A + A == B, B + B == CD, D + D == E", "SEND + MORE == MONEY"]);
+
+    // Rating holds for puzzles without a solution
+    let rating = rate(&Puzzle::parse("A + B == CDE").unwrap());
+    assert_eq!((rating.letters, rating.addends, rating.level), (5, 2, Level::Easy));
+}
+
+#[test]
+fn rate_non_ascii_puzzle() {
+    // Same puzzle as `I + BB == ILL` with Polish letters
+    let rating = rate(&Puzzle::parse("Ł + ŻŻ == ŁĄĄ").unwrap());
+    assert_eq!(rating, rate(&Puzzle::parse("I + BB == ILL").unwrap()));
+    assert_eq!((rating.letters, rating.level), (3, Level::Easy));
+}
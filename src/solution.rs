@@ -0,0 +1,210 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, ops::Index};
+use itertools::Itertools;
+
+use crate::options::SolveOptions;
+use crate::puzzle::{literal_digit, Puzzle, Term};
+use crate::verify;
+
+/// Digit of every letter of a solved puzzle.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Solution {
+    digits: BTreeMap<char, u8>,
+}
+
+impl Solution {
+    pub fn get(&self, letter: char) -> Option<u8> {
+        self.digits.get(&letter).copied()
+    }
+
+    /// Letters with their digits in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (char, u8)> + '_ {
+        self.digits.iter().map(|(&letter, &digit)| (letter, digit))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &u8> {
+        self.digits.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.digits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn to_map(&self) -> HashMap<char, u8> {
+        self.iter().collect()
+    }
+
+    /// Puzzle with every letter replaced by its digit, e.g. `9567 + 1085 == 10652`.
+    /// Letters without a digit, or with one out of the base, are left as they are.
+    pub fn render(&self, puzzle: &Puzzle) -> String {
+        puzzle.equations.iter()
+            .map(|equation| format!("{} == {}", self.render_side(&equation.left, puzzle.base), self.render_side(&equation.right, puzzle.base)))
+            .join(", ")
+    }
+
+    /// Every equation as a written sum, terms of each side right aligned above and below a rule.
+    pub fn render_columns(&self, puzzle: &Puzzle) -> String {
+        puzzle.equations.iter()
+            .map(|equation| {
+                let side = |terms: &[Term]| terms.iter()
+                    .enumerate()
+                    .map(|(idx, term)| {
+                        let operator = match (idx, term.negative) {
+                            (_, true) => "- ",
+                            (0, false) => "  ",
+                            (_, false) => "+ ",
+                        };
+                        (operator, term.factors.iter().map(|word| self.substitute(word, puzzle.base)).join(" * "))
+                    })
+                    .collect::<Vec<_>>();
+                let (left, right) = (side(&equation.left), side(&equation.right));
+                let width = left.iter().chain(right.iter()).map(|(_, term)| term.chars().count()).max().unwrap_or(0);
+
+                let line = |(operator, term): &(&str, String)| format!("{}{:>width$}\n", operator, term, width = width);
+                left.iter().map(line).collect::<String>() + &"-".repeat(width + 2) + "\n" + &right.iter().map(line).collect::<String>()
+            })
+            .join("\n")
+    }
+
+    /// Checks the digits against the puzzle with the default options, without any solver.
+    pub fn verify(&self, puzzle: &Puzzle) -> bool {
+        self.verify_with(puzzle, &SolveOptions::default())
+    }
+
+    /// Same as `verify` with digits allowed and leading zeros decided by the options, see `verify::verify_puzzle`.
+    pub fn verify_with(&self, puzzle: &Puzzle, options: &SolveOptions) -> bool {
+        verify::verify_puzzle(puzzle, &self.to_map(), options).is_ok()
+    }
+
+    fn digit(&self, ch: char) -> Option<u8> {
+        literal_digit(ch).or_else(|| self.get(ch))
+    }
+
+    fn substitute(&self, word: &str, base: u8) -> String {
+        word.chars()
+            .map(|ch| self.digit(ch)
+                .and_then(|digit| char::from_digit(digit as u32, base as u32))
+                .map_or(ch, |digit| digit.to_ascii_uppercase()))
+            .collect()
+    }
+
+    fn render_side(&self, terms: &[Term], base: u8) -> String {
+        terms.iter()
+            .enumerate()
+            .map(|(idx, term)| {
+                let factors = term.factors.iter().map(|word| self.substitute(word, base)).join(" * ");
+                // First term of a side is never negative, the grammar has no leading minus
+                match (idx, term.negative) {
+                    (0, _) => factors,
+                    (_, true) => format!(" - {}", factors),
+                    (_, false) => format!(" + {}", factors),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Indexed like the map of digits it replaces.
+impl Index<&char> for Solution {
+    type Output = u8;
+
+    fn index(&self, letter: &char) -> &u8 {
+        &self.digits[letter]
+    }
+}
+
+impl FromIterator<(char, u8)> for Solution {
+    fn from_iter<I: IntoIterator<Item = (char, u8)>>(iter: I) -> Self {
+        Self { digits: iter.into_iter().collect() }
+    }
+}
+
+impl From<HashMap<char, u8>> for Solution {
+    fn from(digits: HashMap<char, u8>) -> Self {
+        digits.into_iter().collect()
+    }
+}
+
+impl From<Solution> for HashMap<char, u8> {
+    fn from(solution: Solution) -> Self {
+        solution.digits.into_iter().collect()
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.iter().map(|(letter, digit)| format!("{} = {}", letter, digit)).join(", "))
+    }
+}
+
+#[cfg(test)]
+fn send_more_money() -> Solution {
+    [('S', 9), ('E', 5), ('N', 6), ('D', 7), ('M', 1), ('O', 0), ('R', 8), ('Y', 2)].into_iter().collect()
+}
+
+#[test]
+fn render_substituted_puzzle() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(send_more_money().render(&puzzle), "9567 + 1085 == 10652");
+
+    let puzzle = Puzzle::parse("A + A == B, B + B == CD, D + D == E").unwrap();
+    let solution: Solution = [('A', 3), ('B', 6), ('C', 1), ('D', 2), ('E', 4)].into_iter().collect();
+    assert_eq!(solution.render(&puzzle), "3 + 3 == 6, 6 + 6 == 12, 2 + 2 == 4");
+
+    let puzzle = Puzzle::parse("C * D - AB == A - B").unwrap();
+    let solution: Solution = [('A', 1), ('B', 2), ('C', 3), ('D', 4)].into_iter().collect();
+    assert_eq!(solution.render(&puzzle), "3 * 4 - 12 == 1 - 2");
+
+    let puzzle = Puzzle::parse("A + B == C").unwrap();
+    let solution: Solution = [('A', 12), ('B', 1), ('C', 3)].into_iter().collect();
+    assert_eq!(solution.render(&puzzle), "A + 1 == 3");
+    assert_eq!(solution.render_columns(&puzzle), "  A\n+ 1\n---\n  3\n");
+}
+
+#[test]
+fn render_columns() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(send_more_money().render_columns(&puzzle), "   9567\n+  1085\n-------\n  10652\n");
+
+    let puzzle = Puzzle::parse("A + A == B, B + B == CD").unwrap();
+    let solution: Solution = [('A', 3), ('B', 6), ('C', 1), ('D', 2)].into_iter().collect();
+    assert_eq!(solution.render_columns(&puzzle), "  3\n+ 3\n---\n  6\n\n   6\n+  6\n----\n  12\n");
+}
+
+#[test]
+fn verify_solution() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert!(send_more_money().verify(&puzzle));
+
+    let mut digits = send_more_money().to_map();
+    digits.insert('Y', 3);
+    assert!(!Solution::from(digits).verify(&puzzle));
+
+    // Missing letter, shared digit
+    let solution: Solution = send_more_money().iter().filter(|&(letter, _)| letter != 'Y').collect();
+    assert!(!solution.verify(&puzzle));
+    let solution: Solution = [('A', 1), ('B', 1)].into_iter().collect();
+    assert!(!solution.verify(&Puzzle::parse("A == B").unwrap()));
+}
+
+#[test]
+fn verify_leading_zeros_and_options() {
+    let puzzle = Puzzle::parse("AS + A + O == MOM").unwrap();
+    let solution: Solution = [('A', 9), ('S', 2), ('M', 1), ('O', 0)].into_iter().collect();
+    assert!(!solution.verify(&puzzle));
+    assert!(solution.verify_with(&puzzle, &SolveOptions::new().leading_zeros(crate::options::LeadingZeros::SingleLetter)));
+    assert!(!solution.verify_with(&puzzle, &SolveOptions::new().leading_zeros(crate::options::LeadingZeros::SingleLetter).fix('A', 8)));
+}
+
+#[test]
+fn convert_to_and_from_map() {
+    let digits: HashMap<char, u8> = [('A', 1), ('B', 2)].into_iter().collect();
+    let solution = Solution::from(digits.clone());
+    assert_eq!(solution[&'A'], 1);
+    assert_eq!(solution.get('C'), None);
+    assert_eq!(solution.to_string(), "A = 1, B = 2");
+    assert_eq!(HashMap::from(solution), digits);
+}
@@ -1,67 +1,359 @@
 #![allow(unused)]
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashMap, num::NonZeroUsize, sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc, Mutex, OnceLock}, thread, time::{Duration, Instant}};
 use itertools::Itertools;
 
+use crate::bignum::BigInt;
+use crate::options::{LeadingZeros, LetterOrder, SolveOptions};
+use crate::puzzle::{literal_digit, Equation, ParseOptions, Puzzle, Term};
+use crate::solution::Solution;
+use crate::limits::{AbortReason, CancelToken, Limits, SolveOutcome};
+use crate::progress::{Progress, ProgressObserver, ProgressReporter};
+use crate::stats::SolveStats;
+
 #[derive(Debug)]
 struct InputBatch {
     unique_chars: Vec<char>,
-    lines: Vec<String>,
-    result: String,
+    equations: Vec<Equation>,
+    base: u8,
+    leading_zeros: LeadingZeros,
 }
 
 impl InputBatch {
-    fn from_str(input: &str) -> Self {
-        let unique_set: HashSet<char> = input.chars().filter(|ch| ch.is_alphabetic()).collect();
-        let mut split_eq = input.trim().split("==");
+    fn from_puzzle(puzzle: &Puzzle, options: &SolveOptions) -> Self {
+        Self {
+            unique_chars: puzzle.ordered_chars(options.letter_order),
+            equations: puzzle.equations.clone(),
+            base: puzzle.base,
+            leading_zeros: options.leading_zeros,
+        }
+    }
 
-        let mut lines: Vec<String> = split_eq.next().unwrap().split("+").map(|s| s.trim().chars().collect()).collect();
-        lines.sort_by(|a, b| b.len().cmp(&a.len()));
+    fn char_value(ch: char, mapping: &HashMap<char, u8>) -> u8 {
+        literal_digit(ch).unwrap_or_else(|| *mapping.get(&ch).unwrap())
+    }
 
-        let result = split_eq.next().unwrap().trim().chars().collect();
+    /// Value of a word, `None` if it does not fit.
+    fn word_value(&self, word: &str, mapping: &HashMap<char, u8>) -> Option<i128> {
+        word.chars().try_fold(0i128, |acc, ch| acc.checked_mul(self.base as i128)?.checked_add(Self::char_value(ch, mapping) as i128))
+    }
 
-        Self {
-            unique_chars: unique_set.into_iter().collect(),
-            lines,
-            result
-        }
+    fn map_lines_sum(&self, terms: &[Term], mapping: &HashMap<char, u8>) -> Option<i128> {
+        terms.iter().try_fold(0i128, |sum, term| {
+            let value = term.factors.iter().try_fold(1i128, |acc, word| acc.checked_mul(self.word_value(word, mapping)?))?;
+            if term.negative { sum.checked_sub(value) } else { sum.checked_add(value) }
+        })
     }
 
-    fn applly_mapping_to_line(input: &str, mapping: &HashMap<char, u8>) -> Option<u64> {
-        if mapping.get(&input.chars().nth(0).unwrap()).unwrap() == &0 {
-            None
-        } else {
-            Some(input.chars().rev().enumerate().map(|(idx, ch)| 10u64.pow(idx as u32) * *mapping.get(&ch).unwrap() as u64).sum())
-        }
+    /// Same as `map_lines_sum` with arbitrary precision, for sides which do not fit in primitive integers.
+    fn map_lines_big_sum(&self, terms: &[Term], mapping: &HashMap<char, u8>) -> BigInt {
+        terms.iter()
+            .map(|term| {
+                let value = term.factors.iter()
+                    .map(|word| BigInt::from_digits(word.chars().map(|ch| Self::char_value(ch, mapping)), self.base))
+                    .fold(BigInt::from(1), |acc, value| acc * value);
+                if term.negative { -value } else { value }
+            })
+            .fold(BigInt::zero(), |sum, value| sum + value)
     }
 
-    fn map_lines_sum(&self, mapping: &HashMap<char, u8>) -> Option<u64> {
-        self.lines.iter().map(|s| Self::applly_mapping_to_line(s, mapping)).sum()
+    fn has_leading_zero(&self, mapping: &HashMap<char, u8>) -> bool {
+        self.equations.iter()
+            .flat_map(|equation| equation.words())
+            .any(|word| Self::char_value(word.chars().next().unwrap(), mapping) == 0 && self.leading_zeros.forbids_zero(word))
     }
 
-    fn map_line_result(&self, mapping: &HashMap<char, u8>) -> Option<u64> {
-        Self::applly_mapping_to_line(&self.result, mapping)
+    fn check_mapping(&self, mapping: &HashMap<char, u8>) -> bool {
+        self.equations.iter().all(|equation| {
+            match (self.map_lines_sum(&equation.left, mapping), self.map_lines_sum(&equation.right, mapping)) {
+                (Some(left_value), Some(right_value)) => left_value == right_value,
+                _ => self.map_lines_big_sum(&equation.left, mapping) == self.map_lines_big_sum(&equation.right, mapping),
+            }
+        })
     }
 
 }
 
 
-pub fn solve(input: &str) -> Option<HashMap<char, u8>> {
-    let batch = InputBatch::from_str(input);
+/// Search space of the permutations split into parts by digits of the first free letters.
+/// Parts keep the order of the sequential search, so any of them can be searched on its own.
+#[derive(Debug)]
+struct SearchSpace {
+    batch: InputBatch,
+    fixed_mapping: HashMap<char, u8>,
+    free_chars: Vec<char>,
+    possible_values: Vec<u8>,
+}
+
+impl SearchSpace {
+    fn from_puzzle(puzzle: &Puzzle, options: &SolveOptions) -> Option<Self> {
+        let batch = InputBatch::from_puzzle(puzzle, options);
+
+        // Fixed letters are bound upfront, only the rest is permuted, letters without any digit of the base have none to take
+        if batch.unique_chars.iter().any(|&ch| (0..batch.base).all(|digit| !options.allows(ch, digit))) {
+            return None;
+        }
+        let (fixed_chars, free_chars): (Vec<char>, Vec<char>) = batch.unique_chars.iter()
+            .partition(|&&ch| options.fixed_digit(ch, batch.base).is_some());
+        let fixed_mapping: HashMap<char, u8> = fixed_chars.iter()
+            .map(|&ch| (ch, options.fixed_digit(ch, batch.base).unwrap()))
+            .collect();
+        if !fixed_mapping.values().all_unique() {
+            return None;
+        }
+        let possible_values: Vec<u8> = (0..batch.base).filter(|v| !fixed_mapping.values().contains(v)).collect();
+
+        Some(Self { batch, fixed_mapping, free_chars, possible_values })
+    }
+
+    fn partitions(&self) -> Vec<Vec<u8>> {
+        // Two letters give 90 parts in base 10, enough to keep all threads busy till the end
+        let prefix_len = self.free_chars.len().min(2);
+        self.possible_values.iter().copied().permutations(prefix_len).collect()
+    }
+
+    fn partition_size(&self, prefix_len: usize) -> f64 {
+        let values = self.possible_values.len() - prefix_len;
+        let letters = self.free_chars.len() - prefix_len;
+        (values.saturating_sub(letters) + 1..=values).map(|value| value as f64).product()
+    }
+
+    /// Searches a single part, `stop` gets permutations checked so far, permutations iterated in the part and solutions found.
+    fn search_partition(
+        &self,
+        prefix: &[u8],
+        options: &SolveOptions,
+        first_only: bool,
+        mut stop: impl FnMut(u64, usize, usize) -> Option<AbortReason>,
+        stats: &mut SolveStats,
+        solutions: &mut Vec<Solution>,
+    ) -> Result<(), AbortReason> {
+        let rest_values: Vec<u8> = self.possible_values.iter().copied().filter(|v| !prefix.contains(v)).collect();
 
-    let k = batch.unique_chars.len();
+        for (iterated, permutation) in rest_values.into_iter().permutations(self.free_chars.len() - prefix.len()).enumerate() {
+            // Permutations outside the domains count too, so limits hold while skipping them
+            stats.permutations += 1;
+            if let Some(reason) = stop(stats.permutations, iterated, solutions.len()) {
+                return Err(reason);
+            }
+            let mut mapping: HashMap<char, u8> = self.free_chars.iter().copied().zip(prefix.iter().copied().chain(permutation)).collect();
+            if !mapping.iter().all(|(&ch, &v)| options.allows(ch, v)) {
+                continue;
+            }
+            mapping.extend(self.fixed_mapping.iter());
+            if self.batch.has_leading_zero(&mapping) {
+                stats.pruned_leading_zero += 1;
+            } else if !self.batch.check_mapping(&mapping) {
+                stats.pruned_column_mismatch += 1;
+            } else {
+                solutions.push(Solution::from(mapping));
+                if first_only {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
 
-    for permutation in (0..10u8).permutations(k) {
-        let mapping: HashMap<char, u8> = batch.unique_chars.iter().copied().zip(permutation.into_iter()).collect();
+    /// Solutions found before the search was aborted are kept.
+    fn search(&self, options: &SolveOptions, first_only: bool, limits: &Limits, stats: &mut SolveStats, solutions: &mut Vec<Solution>) -> Result<(), AbortReason> {
+        stats.max_depth = self.free_chars.len();
+        let partitions = self.partitions();
+        let partition_size = self.partition_size(partitions[0].len()).max(1.0);
+        let mut reporter = ProgressReporter::new(limits.observer());
 
-        if let (Some(lines_sum_value), Some(result_value)) = (batch.map_lines_sum(&mapping), batch.map_line_result(&mapping)) {
-            if lines_sum_value == result_value {
-                return Some(mapping)
+        for (idx, prefix) in partitions.iter().enumerate() {
+            let stop = |permutations, iterated, found| {
+                reporter.tick(permutations, found, || (idx as f64 + iterated as f64 / partition_size) / partitions.len() as f64);
+                limits.check_throttled(permutations)
+            };
+            self.search_partition(prefix, options, first_only, stop, stats, solutions)?;
+            if first_only && !solutions.is_empty() {
+                break;
             }
         }
+        reporter.finish(stats.permutations, solutions.len(), 1.0);
+        Ok(())
+    }
+
+    /// Same as `search` with parts searched on `threads` threads, sharing the limits and the count of permutations.
+    fn search_parallel(
+        &self,
+        options: &SolveOptions,
+        first_only: bool,
+        threads: usize,
+        limits: &Limits,
+        stats: &mut SolveStats,
+        solutions: &mut Vec<Solution>,
+    ) -> Result<(), AbortReason> {
+        stats.max_depth = self.free_chars.len();
+        let partitions = self.partitions();
+        let next_partition = AtomicUsize::new(0);
+        // Parts after the first one with a solution are not needed when looking for a single solution
+        let first_solved = AtomicUsize::new(usize::MAX);
+        let is_needed = |idx: usize| !first_only || idx < first_solved.load(Ordering::Relaxed);
+        let permutations = AtomicU64::new(0);
+        let searched = AtomicUsize::new(0);
+        let found = AtomicUsize::new(0);
+        let aborted = OnceLock::new();
+        let reporter = Mutex::new(ProgressReporter::new(limits.observer()));
+        let workers_stats = Mutex::new(SolveStats::default());
+
+        let mut solved_partitions: Vec<(usize, Vec<Solution>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| scope.spawn(|| {
+                    let mut stats = SolveStats::default();
+                    let mut solved = vec![];
+                    loop {
+                        let idx = next_partition.fetch_add(1, Ordering::Relaxed);
+                        if idx >= partitions.len() || !is_needed(idx) || aborted.get().is_some() {
+                            workers_stats.lock().unwrap().add(&stats);
+                            break solved;
+                        }
+                        let stop = |_, _, _| {
+                            let checked = permutations.fetch_add(1, Ordering::Relaxed) + 1;
+                            // Part cancelled because of a solution found earlier is not needed at all
+                            if !is_needed(idx) {
+                                return Some(AbortReason::Cancelled);
+                            }
+                            if let Some(&reason) = aborted.get() {
+                                return Some(reason);
+                            }
+                            if checked.is_multiple_of(1024) {
+                                let explored = || searched.load(Ordering::Relaxed) as f64 / partitions.len() as f64;
+                                reporter.lock().unwrap().tick(checked, found.load(Ordering::Relaxed), explored);
+                            }
+                            limits.check_throttled(checked).map(|reason| *aborted.get_or_init(|| reason))
+                        };
+                        let mut solutions = vec![];
+                        if self.search_partition(&partitions[idx], options, first_only, stop, &mut stats, &mut solutions).is_err() && first_only {
+                            solutions.clear();
+                        }
+                        searched.fetch_add(1, Ordering::Relaxed);
+                        if !solutions.is_empty() {
+                            found.fetch_add(solutions.len(), Ordering::Relaxed);
+                            first_solved.fetch_min(idx, Ordering::Relaxed);
+                            solved.push((idx, solutions));
+                        }
+                    }
+                }))
+                .collect();
+            workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+        });
+        stats.add(&workers_stats.into_inner().unwrap());
+
+        // Solutions are ordered as found by the sequential search, regardless of which thread found them
+        solved_partitions.sort_by_key(|(idx, _)| *idx);
+        let found = solved_partitions.into_iter().flat_map(|(_, solutions)| solutions);
+        solutions.extend(if first_only { found.take(1).collect::<Vec<_>>() } else { found.collect() });
+
+        if let Some(&reason) = aborted.get() {
+            return Err(reason);
+        }
+        reporter.into_inner().unwrap().finish(stats.permutations, solutions.len(), 1.0);
+        Ok(())
+    }
+}
+
+fn threads_count(threads: usize) -> usize {
+    if threads == 0 { thread::available_parallelism().map_or(1, NonZeroUsize::get) } else { threads }
+}
+
+pub fn solve(input: &str) -> Option<Solution> {
+    solve_puzzle(&Puzzle::parse(input).ok()?)
+}
+
+pub fn solve_puzzle(puzzle: &Puzzle) -> Option<Solution> {
+    solve_with(puzzle, &SolveOptions::default())
+}
+
+pub fn solve_with(puzzle: &Puzzle, options: &SolveOptions) -> Option<Solution> {
+    solve_with_stats(puzzle, options).0
+}
+
+pub fn solve_with_stats(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, SolveStats) {
+    let (result, stats) = search(puzzle, options, &Limits::default());
+    (result.ok().flatten(), stats)
+}
+
+/// Same as `solve_with`, but the search stops once any of the limits is reached, permutations count as nodes.
+pub fn solve_limited(puzzle: &Puzzle, options: &SolveOptions, limits: &Limits) -> SolveOutcome {
+    match search(puzzle, options, limits) {
+        (Ok(Some(solution)), _) => SolveOutcome::Solved(solution),
+        (Ok(None), _) => SolveOutcome::NoSolution,
+        (Err(reason), partial_stats) => SolveOutcome::Aborted { reason, partial_stats },
+    }
+}
+
+fn search(puzzle: &Puzzle, options: &SolveOptions, limits: &Limits) -> (Result<Option<Solution>, AbortReason>, SolveStats) {
+    let start = Instant::now();
+    let mut stats = SolveStats::default();
+    let mut solutions = vec![];
+    let result = match SearchSpace::from_puzzle(puzzle, options) {
+        Some(space) => space.search(options, true, limits, &mut stats, &mut solutions).map(|_| solutions.pop()),
+        None => Ok(None),
+    };
+    stats.elapsed = start.elapsed();
+    (result, stats)
+}
+
+/// All solutions of the puzzle, in the order of the permutations.
+pub fn solve_all(puzzle: &Puzzle, options: &SolveOptions) -> Vec<Solution> {
+    solve_all_limited(puzzle, options, &Limits::default()).0
+}
+
+/// Same as `solve_all`, but the search stops once any of the limits is reached, with solutions found till then.
+pub fn solve_all_limited(puzzle: &Puzzle, options: &SolveOptions, limits: &Limits) -> (Vec<Solution>, Option<AbortReason>) {
+    let mut solutions = vec![];
+    let aborted = SearchSpace::from_puzzle(puzzle, options)
+        .and_then(|space| space.search(options, false, limits, &mut SolveStats::default(), &mut solutions).err());
+    (solutions, aborted)
+}
+
+/// Same as `solve_with`, but parts of the search space are searched on `threads` threads, 0 uses all available cores.
+/// Threads still busy are cancelled once a solution is found, only parts ordered before it are searched till the end.
+pub fn solve_parallel(puzzle: &Puzzle, options: &SolveOptions, threads: usize) -> Option<Solution> {
+    solve_parallel_with_stats(puzzle, options, threads).0
+}
+
+/// Same as `solve_parallel` with stats of all threads summed up.
+pub fn solve_parallel_with_stats(puzzle: &Puzzle, options: &SolveOptions, threads: usize) -> (Option<Solution>, SolveStats) {
+    let (result, stats) = search_parallel(puzzle, options, threads, &Limits::default());
+    (result.ok().flatten(), stats)
+}
+
+/// Same as `solve_parallel`, but all threads stop once any of the limits is reached, permutations of all threads count.
+pub fn solve_parallel_limited(puzzle: &Puzzle, options: &SolveOptions, threads: usize, limits: &Limits) -> SolveOutcome {
+    match search_parallel(puzzle, options, threads, limits) {
+        (Ok(Some(solution)), _) => SolveOutcome::Solved(solution),
+        (Ok(None), _) => SolveOutcome::NoSolution,
+        (Err(reason), partial_stats) => SolveOutcome::Aborted { reason, partial_stats },
     }
-    None
 }
 
+fn search_parallel(puzzle: &Puzzle, options: &SolveOptions, threads: usize, limits: &Limits) -> (Result<Option<Solution>, AbortReason>, SolveStats) {
+    let start = Instant::now();
+    let mut stats = SolveStats::default();
+    let mut solutions = vec![];
+    let result = match SearchSpace::from_puzzle(puzzle, options) {
+        Some(space) => space.search_parallel(options, true, threads_count(threads), limits, &mut stats, &mut solutions).map(|_| solutions.pop()),
+        None => Ok(None),
+    };
+    stats.elapsed = start.elapsed();
+    (result, stats)
+}
+
+/// Same as `solve_all`, but parts of the search space are searched on `threads` threads, 0 uses all available cores.
+pub fn solve_all_parallel(puzzle: &Puzzle, options: &SolveOptions, threads: usize) -> Vec<Solution> {
+    solve_all_parallel_limited(puzzle, options, threads, &Limits::default()).0
+}
+
+/// Same as `solve_all_parallel`, but all threads stop once any of the limits is reached, with solutions found till then.
+pub fn solve_all_parallel_limited(puzzle: &Puzzle, options: &SolveOptions, threads: usize, limits: &Limits) -> (Vec<Solution>, Option<AbortReason>) {
+    let mut solutions = vec![];
+    let aborted = SearchSpace::from_puzzle(puzzle, options)
+        .and_then(|space| space.search_parallel(options, false, threads_count(threads), limits, &mut SolveStats::default(), &mut solutions).err());
+    (solutions, aborted)
+}
 
 #[test]
 fn puzzle_with_three_letters() {
@@ -160,4 +452,381 @@ fn puzzle_with_ten_letters() {
     .into_iter()
     .collect();
     assert_eq!(answer, Some(expected));
-}
\ No newline at end of file
+}
+
+#[test]
+fn product_puzzle_with_three_letters() {
+    let answer = solve("NO * NO == ONE");
+    let expected = [('N', 2), ('O', 7), ('E', 9)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn product_puzzle_with_six_letters() {
+    let answer = solve("ABC * ABC == DEFABC");
+    let expected = [('A', 6), ('B', 2), ('C', 5), ('D', 3), ('E', 9), ('F', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn product_puzzle_without_solution() {
+    let answer = solve("A * BC == BD");
+    assert_eq!(answer, None);
+}
+
+#[test]
+fn system_of_three_equations() {
+    let answer = solve("A + A == B, B + B == CD, D + D == E");
+    let expected = [('A', 3), ('B', 6), ('C', 1), ('D', 2), ('E', 4)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn system_of_equations_on_separate_lines() {
+    let answer = solve("A + A + A == B\nB + B == CD\n");
+    let expected = [('A', 3), ('B', 9), ('C', 1), ('D', 8)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn system_without_common_solution() {
+    let answer = solve("A + A == B, B + B == A");
+    assert_eq!(answer, None);
+}
+
+#[test]
+fn puzzle_with_difference() {
+    let answer = solve("MONEY - MORE == SEND");
+    let expected = [
+        ('S', 9),
+        ('E', 5),
+        ('N', 6),
+        ('D', 7),
+        ('M', 1),
+        ('O', 0),
+        ('R', 8),
+        ('Y', 2),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn puzzle_with_words_on_both_sides() {
+    let answer = solve("ABC + ABC == DB + DB + DB + D");
+    let expected = [('A', 1), ('B', 2), ('C', 7), ('D', 8)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn puzzle_with_single_equality_sign() {
+    let answer = solve("AS + A = MOM");
+    let expected = [('A', 9), ('S', 2), ('M', 1), ('O', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn puzzle_in_base_8() {
+    let puzzle = Puzzle::parse_with("AS + A == MOM", &ParseOptions::new().base(8)).unwrap();
+    let expected = [('A', 7), ('S', 2), ('M', 1), ('O', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_in_base_12() {
+    let puzzle = Puzzle::parse_with("A + A + A + A + A + A + A + A + A + A + A + A + A + B == BCC", &ParseOptions::new().base(12)).unwrap();
+    let expected = [('A', 11), ('B', 1), ('C', 0)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_in_base_16() {
+    let puzzle = Puzzle::parse_with("I + BB == ILL", &ParseOptions::new().base(16)).unwrap();
+    let expected = [('I', 1), ('B', 15), ('L', 0)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_with_lowercase_letters() {
+    let puzzle = Puzzle::parse_with("as + A == mom", &ParseOptions::new().case_insensitive(true)).unwrap();
+    let expected = [('A', 9), ('S', 2), ('M', 1), ('O', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_with_non_ascii_letters() {
+    let answer = solve("Ł + ŻŻ == ŁĄĄ");
+    let expected = [('Ł', 1), ('Ż', 9), ('Ą', 0)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn puzzle_with_literal_digits() {
+    let puzzle = Puzzle::parse_with("I + B9 == 1LL", &ParseOptions::new().literal_digits(true)).unwrap();
+    let expected = [('I', 1), ('B', 9), ('L', 0)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn literal_leading_zero_is_invalid() {
+    let puzzle = Puzzle::parse_with("0A + B == C", &ParseOptions::new().literal_digits(true)).unwrap();
+    assert_eq!(solve_puzzle(&puzzle), None);
+}
+
+#[test]
+fn puzzle_with_fixed_letter() {
+    let puzzle = Puzzle::parse("A + A == B").unwrap();
+    let expected = [('A', 3), ('B', 6)].into_iter().collect();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('A', 3)), Some(expected));
+}
+
+#[test]
+fn fixed_digit_out_of_base() {
+    let puzzle = Puzzle::parse("A + A == B").unwrap();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('B', 12)), None);
+    assert_eq!(solve_all(&puzzle, &SolveOptions::new().domain('A', [2, 12])), vec![[('A', 2), ('B', 4)].into_iter().collect()]);
+}
+
+#[test]
+fn puzzle_with_restricted_domains() {
+    let puzzle = Puzzle::parse("A + A == B").unwrap();
+    let options = SolveOptions::new()
+        .domain('A', [1, 3, 5, 7, 9])
+        .domain('B', [8, 9]);
+    assert_eq!(solve_with(&puzzle, &options), None);
+    let options = SolveOptions::new()
+        .domain('A', [1, 3, 5, 7, 9])
+        .domain('B', [6, 7]);
+    let expected = [('A', 3), ('B', 6)].into_iter().collect();
+    assert_eq!(solve_with(&puzzle, &options), Some(expected));
+}
+
+#[test]
+fn contradicting_hints_have_no_solution() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('E', 4)), None);
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('E', 5).fix('N', 5)), None);
+}
+
+#[test]
+fn leading_zeros_policies() {
+    let puzzle = Puzzle::parse("ACA + DD == BD").unwrap();
+    assert_eq!(solve_puzzle(&puzzle), None);
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::SingleLetter)), None);
+    let answer = solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::Allowed)).unwrap();
+    assert_eq!(answer[&'A'], 0);
+    assert_eq!(answer[&'C'] + answer[&'D'], answer[&'B']);
+}
+
+#[test]
+fn single_letter_word_may_be_zero() {
+    let puzzle = Puzzle::parse("AS + A + O == MOM").unwrap();
+    assert_eq!(solve_puzzle(&puzzle), None);
+    let expected = [('A', 9), ('S', 2), ('M', 1), ('O', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::SingleLetter)), Some(expected));
+}
+
+#[test]
+fn all_solutions_of_puzzle() {
+    let puzzle = Puzzle::parse("A + B == C").unwrap();
+    let solutions = solve_all(&puzzle, &SolveOptions::default());
+    assert_eq!(solutions.len(), 32);
+    assert!(solutions.iter().all(|solution| solution[&'A'] + solution[&'B'] == solution[&'C']));
+    assert_eq!(solutions.first(), solve_puzzle(&puzzle).as_ref());
+}
+
+#[test]
+fn parallel_search_does_not_depend_on_threads_count() {
+    let puzzle = Puzzle::parse("NO + NO + TOO == LATE").unwrap();
+    let options = SolveOptions::default();
+    for threads in [0, 1, 2, 3, 8] {
+        assert_eq!(solve_parallel(&puzzle, &options, threads), solve_with(&puzzle, &options));
+    }
+
+    let puzzle = Puzzle::parse("A + B == C").unwrap();
+    for threads in [0, 1, 2, 3, 8] {
+        assert_eq!(solve_parallel(&puzzle, &options, threads), solve_with(&puzzle, &options));
+        assert_eq!(solve_all_parallel(&puzzle, &options, threads), solve_all(&puzzle, &options));
+    }
+}
+
+#[test]
+fn parallel_search_without_solution() {
+    let puzzle = Puzzle::parse("ACA + DD == BD").unwrap();
+    assert_eq!(solve_parallel(&puzzle, &SolveOptions::default(), 4), None);
+    assert!(solve_all_parallel(&puzzle, &SolveOptions::default(), 4).is_empty());
+
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(solve_parallel(&puzzle, &SolveOptions::new().fix('E', 5).fix('N', 5), 4), None);
+}
+
+#[test]
+fn parallel_search_with_fixed_letters() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let options = SolveOptions::new().fix('S', 9).fix('E', 5).fix('N', 6).fix('M', 1);
+    let expected = [('S', 9), ('E', 5), ('N', 6), ('D', 7), ('M', 1), ('O', 0), ('R', 8), ('Y', 2)].into_iter().collect();
+    assert_eq!(solve_parallel(&puzzle, &options, 4), Some(expected));
+}
+
+#[test]
+fn first_solution_follows_letter_order() {
+    let puzzle = Puzzle::parse("C + B == A").unwrap();
+    let expected = [('C', 1), ('B', 2), ('A', 3)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+
+    let options = SolveOptions::new().letter_order(LetterOrder::Alphabetical);
+    let expected = [('A', 3), ('B', 1), ('C', 2)].into_iter().collect();
+    assert_eq!(solve_with(&puzzle, &options), Some(expected));
+}
+
+#[test]
+fn first_solution_is_reproducible() {
+    let first = solve("A + B == C");
+    assert!(first.is_some());
+    for _ in 0..10 {
+        assert_eq!(solve("A + B == C"), first);
+    }
+}
+
+#[test]
+fn stats_of_search() {
+    let (answer, stats) = solve_with_stats(&Puzzle::parse("I + BB == ILL").unwrap(), &SolveOptions::default());
+    assert!(answer.is_some());
+    assert_eq!(stats.max_depth, 3);
+    assert_eq!(stats.permutations, stats.pruned_leading_zero + stats.pruned_column_mismatch + 1);
+    assert!(stats.pruned_leading_zero > 0);
+    assert!(stats.pruned_column_mismatch > 0);
+}
+
+#[test]
+fn search_aborted_by_limits() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let SolveOutcome::Aborted { reason, partial_stats } = solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().max_nodes(100)) else {
+        panic!("search should be aborted");
+    };
+    assert_eq!(reason, AbortReason::NodeLimit);
+    assert_eq!(partial_stats.permutations, 101);
+
+    let outcome = solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().timeout(Duration::ZERO));
+    assert!(matches!(outcome, SolveOutcome::Aborted { reason: AbortReason::Deadline, .. }));
+
+    let token = CancelToken::new();
+    token.cancel();
+    let outcome = solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().cancel_token(token));
+    assert!(matches!(outcome, SolveOutcome::Aborted { reason: AbortReason::Cancelled, .. }));
+
+    let outcome = solve_limited(&Puzzle::parse("I + BB == ILL").unwrap(), &SolveOptions::default(), &Limits::new().max_nodes(1000));
+    assert_eq!(outcome, SolveOutcome::Solved([('I', 1), ('B', 9), ('L', 0)].into_iter().collect()));
+
+    // Permutations outside the domains are limited too
+    let options = SolveOptions::new().domain('S', [0, 1]).domain('E', [0, 1]).domain('M', [0, 1]);
+    let outcome = solve_limited(&puzzle, &options, &Limits::new().max_nodes(100));
+    assert!(matches!(outcome, SolveOutcome::Aborted { reason: AbortReason::NodeLimit, .. }));
+}
+
+#[test]
+fn search_cancelled_from_another_thread() {
+    let puzzle = Puzzle::parse("A + B + C + D + E + F + G + H + I == JJ").unwrap();
+    let token = CancelToken::new();
+    let limits = Limits::new().cancel_token(token.clone());
+    let outcome = thread::scope(|scope| {
+        let worker = scope.spawn(|| solve_limited(&puzzle, &SolveOptions::default(), &limits));
+        token.cancel();
+        worker.join().unwrap()
+    });
+    assert!(matches!(outcome, SolveOutcome::Aborted { reason: AbortReason::Cancelled, .. }));
+}
+
+#[test]
+fn progress_is_reported() {
+    let reports = Arc::new(Mutex::new(vec![]));
+    let observer = {
+        let reports = reports.clone();
+        ProgressObserver::new(Duration::ZERO, move |progress: &Progress| reports.lock().unwrap().push(progress.clone()))
+    };
+    let puzzle = Puzzle::parse("NO + NO + TOO == LATE").unwrap();
+    let (solutions, aborted) = solve_all_limited(&puzzle, &SolveOptions::default(), &Limits::new().progress(observer));
+    assert_eq!(aborted, None);
+
+    let reports = reports.lock().unwrap();
+    assert!(reports.len() > 100);
+    let last = reports.last().unwrap();
+    assert_eq!(last.explored, 1.0);
+    assert_eq!(last.solutions, solutions.len());
+    assert_eq!(last.nodes, 151_200);
+    assert!(reports.windows(2).all(|pair| pair[0].explored <= pair[1].explored && pair[0].nodes <= pair[1].nodes));
+}
+
+#[test]
+fn parallel_search_bounded_by_limits() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let SolveOutcome::Aborted { reason, partial_stats } = solve_parallel_limited(&puzzle, &SolveOptions::default(), 4, &Limits::new().max_nodes(100)) else {
+        panic!("search should be aborted");
+    };
+    assert_eq!(reason, AbortReason::NodeLimit);
+    // Other threads may have drawn a permutation each before seeing the limit
+    assert!((101..=104).contains(&partial_stats.permutations));
+
+    let outcome = solve_parallel_limited(&puzzle, &SolveOptions::default(), 4, &Limits::new().timeout(Duration::ZERO));
+    assert!(matches!(outcome, SolveOutcome::Aborted { reason: AbortReason::Deadline, .. }));
+
+    let puzzle = Puzzle::parse("A + B + C + D + E + F + G + H + I == JJ").unwrap();
+    let token = CancelToken::new();
+    let limits = Limits::new().cancel_token(token.clone());
+    let (solutions, aborted) = thread::scope(|scope| {
+        let worker = scope.spawn(|| solve_all_parallel_limited(&puzzle, &SolveOptions::default(), 4, &limits));
+        token.cancel();
+        worker.join().unwrap()
+    });
+    assert_eq!(aborted, Some(AbortReason::Cancelled));
+    assert!(solutions.iter().all(|solution| solution.verify(&puzzle)));
+
+    let puzzle = Puzzle::parse("I + BB == ILL").unwrap();
+    let outcome = solve_parallel_limited(&puzzle, &SolveOptions::default(), 4, &Limits::new().max_nodes(1000));
+    assert_eq!(outcome, SolveOutcome::Solved([('I', 1), ('B', 9), ('L', 0)].into_iter().collect()));
+}
+
+#[test]
+fn parallel_search_stats_and_progress() {
+    let puzzle = Puzzle::parse("NO + NO + TOO == LATE").unwrap();
+    let (solution, stats) = solve_parallel_with_stats(&puzzle, &SolveOptions::default(), 4);
+    assert_eq!(solution, solve_puzzle(&puzzle));
+    assert_eq!(stats.max_depth, 6);
+    assert!(stats.permutations > 0 && stats.pruned_column_mismatch > 0);
+
+    let reports = Arc::new(Mutex::new(vec![]));
+    let observer = {
+        let reports = reports.clone();
+        ProgressObserver::new(Duration::ZERO, move |progress: &Progress| reports.lock().unwrap().push(progress.clone()))
+    };
+    let (solutions, aborted) = solve_all_parallel_limited(&puzzle, &SolveOptions::default(), 4, &Limits::new().progress(observer));
+    assert_eq!((solutions, aborted), (solve_all(&puzzle, &SolveOptions::default()), None));
+    let reports = reports.lock().unwrap();
+    assert!(reports.len() > 100);
+    assert_eq!((reports.last().unwrap().explored, reports.last().unwrap().nodes), (1.0, 151_200));
+}
+
+#[test]
+fn words_longer_than_u64_digits() {
+    // Sides of 40 digits do not fit in 128 bits either
+    let input = format!("{} + {} == {}", "AB".repeat(20), "BA".repeat(20), "C".repeat(40));
+    let answer = solve(&input);
+    let expected = [('A', 1), ('B', 2), ('C', 3)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+
+    let input = format!("{} * B == {}", "A".repeat(40), "C".repeat(40));
+    let answer = solve(&input).unwrap();
+    assert_eq!(answer, [('A', 2), ('B', 3), ('C', 6)].into_iter().collect());
+    assert_eq!(crate::verify::verify(&input, &answer.to_map()), Ok(()));
+}
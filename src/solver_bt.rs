@@ -1,170 +1,571 @@
 #![allow(unused)]
-use std::{char, collections::{HashMap, HashSet}};
+use std::{cmp::Reverse, collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
 use itertools::Itertools;
 
-type Column = (HashMap<char, u8>, char);
-type Mapping = HashMap<char, Option<u8>>;
+use crate::bignum::BigInt;
+use crate::options::{Heuristic, LeadingZeros, LetterOrder, SolveOptions};
+use crate::puzzle::{literal_digit, Equation, ParseOptions, Puzzle};
+use crate::solution::Solution;
+use crate::limits::{AbortReason, CancelToken, Limits, SolveOutcome};
+use crate::progress::{Progress, ProgressObserver, ProgressReporter};
+use crate::stats::SolveStats;
+use crate::trace::{BacktrackReason, Trace, TraceEvent};
 
-#[derive(Debug)]
-struct InputBatch {
-    unique_chars: Vec<char>,
-    lines: Vec<String>,
-    result: String,
-    columns_count: usize,
-    columns: Vec<Column>,
+/// Letters never outnumber digits of the biggest supported base.
+pub(crate) const MAX_LETTERS: usize = 36;
+pub(crate) const UNMAPPED: u8 = u8::MAX;
+
+/// Digit of every letter indexed like `InputBatch::unique_chars`, `UNMAPPED` for letters not drawn yet.
+pub(crate) type Mapping = [u8; MAX_LETTERS];
+
+/// Char of a word - index of a letter or a literal digit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Symbol {
+    Letter(usize),
+    Digit(u8),
 }
 
-impl InputBatch {
-    fn from_str(input: &str) -> Self {
-        let unique_set: HashSet<char> = input.chars().filter(|ch| ch.is_alphabetic()).collect();
-        let mut split_eq = input.trim().split("==");
-
-        let mut lines: Vec<String> = split_eq.next().unwrap().split("+").map(|s| s.trim().chars().rev().collect()).collect();
-        lines.sort_by(|a, b| b.len().cmp(&a.len()));
-
-        let result: String = split_eq.next().unwrap().trim().chars().rev().collect();
-        let columns_count = result.len();
-
-        fn get_column_chars(col_idx: usize, lines: &Vec<String>) -> Vec<char> {
-            let mut lines_chars = vec![];
-            let mut lines_iter = lines.iter();
-            while let Some(line) = lines_iter.next() {
-                if let Some(line_char) = line.chars().nth(col_idx) {
-                    lines_chars.push(line_char);
-                } else { break; }
-            }
-            lines_chars
+impl Symbol {
+    pub(crate) fn value(self, mapping: &Mapping) -> u8 {
+        match self {
+            Symbol::Letter(idx) => mapping[idx],
+            Symbol::Digit(digit) => digit,
         }
+    }
+}
+
+/// Signed count of every letter in a column, letters of the right side count as negative,
+/// and the signed sum of literal digits of the column.
+pub(crate) type Column = (Vec<(usize, i32)>, i32);
+
+/// Term with words reversed, so index of a symbol is its column.
+#[derive(Debug)]
+pub(crate) struct EquationTerm {
+    pub(crate) negative: bool,
+    pub(crate) factors: Vec<Vec<Symbol>>,
+}
 
-        let columns: Vec<_> = (0..columns_count)
-            .map(|idx| get_column_chars(idx, &lines).iter()
-                .fold(HashMap::new(), |mut acc, &el| {
-                    *acc.entry(el).or_insert(0u8) += 1;
-                    acc
-                }))
-            .zip(result.chars())
+/// Single equation of the puzzle with terms of the right side moved to the left, so all terms sum up to zero.
+#[derive(Debug)]
+pub(crate) struct EquationBatch {
+    pub(crate) terms: Vec<EquationTerm>,
+    pub(crate) is_linear: bool,
+    pub(crate) columns: Vec<Column>,
+}
+
+impl EquationBatch {
+    fn from_equation(equation: &Equation, columns_count: usize, letters_idx: &HashMap<char, usize>) -> Self {
+        let terms: Vec<EquationTerm> = equation.signed_terms()
+            .map(|(negative, term)| EquationTerm {
+                negative,
+                factors: term.factors.iter()
+                    .map(|word| word.chars()
+                        .rev()
+                        .map(|ch| literal_digit(ch).map_or_else(|| Symbol::Letter(letters_idx[&ch]), Symbol::Digit))
+                        .collect())
+                    .collect(),
+            })
             .collect();
 
+        // Columns with letters coefficients only describe sums and differences, products are checked modulo power of base
+        let columns: Vec<_> = if equation.is_linear() {
+            (0..columns_count)
+                .map(|idx| terms.iter()
+                    .filter_map(|term| term.factors[0].get(idx).map(|&symbol| (symbol, if term.negative { -1 } else { 1 })))
+                    .fold((Vec::new(), 0i32), |mut acc: Column, (symbol, coefficient)| {
+                        match symbol {
+                            Symbol::Digit(digit) => acc.1 += coefficient * digit as i32,
+                            Symbol::Letter(letter) => match acc.0.iter_mut().find(|(idx, _)| *idx == letter) {
+                                Some((_, value)) => *value += coefficient,
+                                None => acc.0.push((letter, coefficient)),
+                            },
+                        }
+                        acc
+                    }))
+                .collect()
+        } else {
+            vec![]
+        };
+
         Self {
-            unique_chars: unique_set.into_iter().collect(),
-            lines,
-            result,
-            columns_count,
+            terms,
+            is_linear: equation.is_linear(),
             columns,
         }
     }
+
+    pub(crate) fn words(&self) -> impl Iterator<Item = &Vec<Symbol>> {
+        self.terms.iter().flat_map(|term| term.factors.iter())
+    }
 }
 
-fn apply_mapping_to_column(last_remainder: u8, col: &Column, mapping: &Mapping) -> Option<u8> {
-    let lines_sum_value: u32 = (last_remainder as u32) + col.0.iter().map(|(k, &v)| v as u32 * mapping.get(k).unwrap().unwrap() as u32).sum::<u32>();
-    let result_value = mapping.get(&col.1).unwrap().unwrap() as u32;
-    if lines_sum_value % 10 == result_value { Some((lines_sum_value / 10) as u8) } else { None }
+#[derive(Debug)]
+pub(crate) struct InputBatch {
+    pub(crate) unique_chars: Vec<char>,
+    /// First symbols of words which may not start with zero.
+    pub(crate) leading: Vec<Symbol>,
+    pub(crate) equations: Vec<EquationBatch>,
+    pub(crate) columns_count: usize,
+    pub(crate) columns_letters: Vec<Vec<usize>>,
+    /// Bitmask of digits allowed for every letter.
+    pub(crate) domains: Vec<u64>,
+    pub(crate) base: u8,
+    pub(crate) heuristic: Heuristic,
 }
 
-fn check_no_leftside_zeros(batch: &InputBatch, mapping: &Mapping) -> bool {
-    for line in batch.lines.iter() {
-        if let Some(left_side_char) = line.chars().last() {
-            if let Some(left_side_value_option) = mapping.get(&left_side_char) {
-                if let Some(left_side_value) = left_side_value_option {
-                    if left_side_value == &0 {
-                        return false;
-                    }
+impl InputBatch {
+    pub(crate) fn from_puzzle(puzzle: &Puzzle, options: &SolveOptions) -> Self {
+        let unique_chars = puzzle.ordered_chars(options.letter_order);
+        let letters_idx: HashMap<char, usize> = unique_chars.iter().enumerate().map(|(idx, &ch)| (ch, idx)).collect();
+        let columns_count = puzzle.equations.iter()
+            .flat_map(|equation| equation.words())
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap();
+
+        // Every equation is padded to the same columns count, so constraints of all of them are checked together
+        let equations: Vec<_> = puzzle.equations.iter()
+            .map(|equation| EquationBatch::from_equation(equation, columns_count, &letters_idx))
+            .collect();
+
+        let leading: Vec<Symbol> = puzzle.equations.iter()
+            .flat_map(|equation| equation.words())
+            .filter(|word| options.forbids_leading_zero(word))
+            .filter_map(|word| word.chars().next())
+            .map(|ch| literal_digit(ch).map_or_else(|| Symbol::Letter(letters_idx[&ch]), Symbol::Digit))
+            .unique()
+            .collect();
+
+        let starting_letters: Vec<usize> = equations.iter()
+            .flat_map(|equation| equation.words())
+            .filter_map(|word| match word.last() {
+                Some(&Symbol::Letter(letter)) => Some(letter),
+                _ => None,
+            })
+            .collect();
+        let column_coefficient = |column_idx: usize, letter: usize| equations.iter()
+            .filter_map(|equation| equation.columns.get(column_idx))
+            .flat_map(|(letters, _)| letters.iter())
+            .filter(|&&(idx, _)| idx == letter)
+            .map(|&(_, coefficient)| coefficient.abs())
+            .sum::<i32>();
+
+        let columns_letters = (0..columns_count)
+            .map(|idx| {
+                // Letters of a column are drawn in the requested letter order, reordered by static heuristics
+                let mut letters: Vec<usize> = equations.iter()
+                    .flat_map(|equation| equation.words())
+                    .filter_map(|word| match word.get(idx) {
+                        Some(&Symbol::Letter(letter)) => Some(letter),
+                        _ => None,
+                    })
+                    .unique()
+                    .sorted()
+                    .collect();
+                match options.heuristic {
+                    Heuristic::LeadingFirst => letters.sort_by_key(|letter| !starting_letters.contains(letter)),
+                    Heuristic::LargestCoefficient => letters.sort_by_key(|&letter| Reverse(column_coefficient(idx, letter))),
+                    Heuristic::Static | Heuristic::MinimumRemainingValues => {}
                 }
-            }
+                letters
+            })
+            .collect();
+
+        // Letters which may not start with zero never take it
+        let domains = unique_chars.iter()
+            .enumerate()
+            .map(|(idx, &ch)| (0..puzzle.base)
+                .filter(|&digit| options.allows(ch, digit))
+                .filter(|&digit| digit != 0 || !leading.contains(&Symbol::Letter(idx)))
+                .fold(0u64, |acc, digit| acc | 1 << digit))
+            .collect();
+
+        Self {
+            unique_chars,
+            leading,
+            equations,
+            columns_count,
+            columns_letters,
+            domains,
+            base: puzzle.base,
+            heuristic: options.heuristic,
         }
     }
-    true
+
+    pub(crate) fn result(&self, mapping: &Mapping) -> Solution {
+        self.unique_chars.iter().copied().zip(mapping.iter().copied()).collect()
+    }
+}
+
+/// Search state updated in place, so the recursion does not allocate.
+/// Carries are stored per column and equation, carry into column `c` of equation `e` at `c * equations count + e`.
+#[derive(Debug)]
+struct State {
+    mapping: Mapping,
+    used: u64,
+    carries: Vec<i32>,
+    stats: SolveStats,
+    limits: Limits,
+    aborted: Option<AbortReason>,
+    reporter: ProgressReporter,
+    // Index of the digit being tried and digits count for every letter drawn so far
+    path: Vec<(u32, u32)>,
+    trace: Option<Trace>,
 }
 
-fn build_result(column_idx: usize, batch: &InputBatch, last_remainder: u8, mapping: &Mapping, column: &Column) -> Option<Mapping> {
-    let column_solution = apply_mapping_to_column(last_remainder, column, mapping);
-
-    if let Some(last_remainder) = column_solution {
-        // Check if has any zero on left side in sum lines
-        let all_mapping_done = mapping.values().all(|v| v.is_some());
-        if all_mapping_done {
-            let all_left_not_zero = batch.lines.iter()
-                .filter_map(|s| s.chars().last())
-                .map(|c| mapping.get(&c).unwrap().unwrap())
-                .all(|v| v != 0);
-            if !all_left_not_zero {
-                return None;
-            }
-
-            // Check if has zero on left side of result
-            if mapping.get(&batch.result.chars().last().unwrap()).unwrap().unwrap() == 0 {
-                return None;
-            }
+impl State {
+    /// Builds the event only when tracing.
+    fn record(&mut self, event: impl FnOnce(&Self) -> TraceEvent) {
+        if self.trace.is_none() {
+            return;
         }
+        let event = event(self);
+        if let Some(trace) = &mut self.trace {
+            trace.events.push(event);
+        }
+    }
+}
+
+/// Every drawn letter splits the share of the search space left by the letters before it evenly between its digits.
+fn explored_fraction(path: &[(u32, u32)]) -> f64 {
+    let mut share = 1.0;
+    let mut explored = 0.0;
+    for &(digit_idx, digits_count) in path {
+        share /= digits_count as f64;
+        explored += digit_idx as f64 * share;
+    }
+    explored
+}
+
+fn apply_mapping_to_column(carry: i32, col: &Column, base: u8, mapping: &Mapping) -> Option<i32> {
+    let base = base as i32;
+    let column_value: i32 = carry + col.1 + col.0.iter().map(|&(idx, v)| v * mapping[idx] as i32).sum::<i32>();
+    if column_value.rem_euclid(base) == 0 { Some(column_value / base) } else { None }
+}
+
+fn signed_value(negative: bool, value: i128) -> i128 {
+    if negative { -value } else { value }
+}
 
-        // Seems valid
-        return if column_idx == batch.columns_count - 1 {
-            Some(mapping.clone())
+fn apply_mapping_modulo(column_idx: usize, equation: &EquationBatch, base: u8, mapping: &Mapping) -> bool {
+    // Lowest digits of sums, differences and products depend only on the same count of lowest digits of the words
+    let modulus = match (base as i128).checked_pow(column_idx as u32 + 1) {
+        Some(modulus) if modulus <= i64::MAX as i128 => modulus,
+        // Product of such remainders could overflow, leave it for the exact check of the last column
+        _ => return true,
+    };
+    let truncated_value = |word: &Vec<Symbol>| word[..word.len().min(column_idx + 1)]
+        .iter()
+        .rev()
+        .fold(0i128, |acc, symbol| acc * base as i128 + symbol.value(mapping) as i128);
+
+    equation.terms.iter()
+        .map(|term| signed_value(term.negative, term.factors.iter().fold(1i128, |acc, word| acc * truncated_value(word) % modulus)))
+        .fold(0i128, |acc, value| (acc + value).rem_euclid(modulus)) == 0
+}
+
+fn apply_mapping_to_equation(equation: &EquationBatch, base: u8, mapping: &Mapping) -> bool {
+    let word_value = |word: &Vec<Symbol>| word.iter()
+        .rev()
+        .try_fold(0i128, |acc, symbol| acc.checked_mul(base as i128)?.checked_add(symbol.value(mapping) as i128));
+
+    let sum = equation.terms.iter()
+        .map(|term| term.factors.iter().try_fold(1i128, |acc, word| acc.checked_mul(word_value(word)?)).map(|value| signed_value(term.negative, value)))
+        .try_fold(0i128, |acc, value| acc.checked_add(value?));
+    match sum {
+        Some(sum) => sum == 0,
+        // Values which do not fit are evaluated again with arbitrary precision
+        None => equation.terms.iter()
+            .map(|term| {
+                let value = term.factors.iter()
+                    .map(|word| BigInt::from_digits(word.iter().rev().map(|symbol| symbol.value(mapping)), base))
+                    .fold(BigInt::from(1), |acc, value| acc * value);
+                if term.negative { -value } else { value }
+            })
+            .fold(BigInt::zero(), |acc, value| acc + value)
+            .is_zero(),
+    }
+}
+
+fn check_column(column_idx: usize, batch: &InputBatch, carries: &mut [i32], mapping: &Mapping) -> bool {
+    let equations_count = batch.equations.len();
+    for (equation_idx, equation) in batch.equations.iter().enumerate() {
+        let carry = carries[column_idx * equations_count + equation_idx];
+        let next_carry = if equation.is_linear {
+            apply_mapping_to_column(carry, &equation.columns[column_idx], batch.base, mapping)
         } else {
-            find_column_mapping(
-                column_idx + 1, 
-                batch, 
-                last_remainder, 
-                mapping.clone())
+            apply_mapping_modulo(column_idx, equation, batch.base, mapping).then_some(0)
+        };
+        match next_carry {
+            Some(next_carry) => carries[(column_idx + 1) * equations_count + equation_idx] = next_carry,
+            None => return false,
         }
     }
-    None
+    true
+}
+
+fn check_last_column(batch: &InputBatch, carries: &[i32], mapping: &Mapping) -> bool {
+    // Nothing may be carried out of the last column, products need the exact check
+    let carries = &carries[batch.columns_count * batch.equations.len()..];
+    batch.equations.iter()
+        .zip(carries)
+        .all(|(equation, &carry)| if equation.is_linear {
+            carry == 0
+        } else {
+            apply_mapping_to_equation(equation, batch.base, mapping)
+        })
+}
+
+fn check_no_leftside_zeros(batch: &InputBatch, mapping: &Mapping) -> bool {
+    batch.leading.iter().all(|symbol| symbol.value(mapping) != 0)
+}
+
+/// Checks a complete mapping against every column of every equation.
+pub(crate) fn check_mapping(batch: &InputBatch, mapping: &Mapping) -> bool {
+    if !check_no_leftside_zeros(batch, mapping) {
+        return false;
+    }
+    let mut carries = vec![0; (batch.columns_count + 1) * batch.equations.len()];
+    (0..batch.columns_count).all(|column_idx| check_column(column_idx, batch, &mut carries, mapping))
+        && check_last_column(batch, &carries, mapping)
+}
+
+fn symbol_char(symbol: Symbol, batch: &InputBatch) -> char {
+    match symbol {
+        Symbol::Letter(idx) => batch.unique_chars[idx],
+        Symbol::Digit(digit) => char::from_digit(digit as u32, 10).unwrap(),
+    }
 }
 
-fn find_column_mapping(column_idx: usize, batch: &InputBatch, last_remainder: u8, mut mapping: Mapping) -> Option<Mapping> {
-    let column = &batch.columns[column_idx];
+/// Column of every equation written out with letters and with digits, e.g. `D + E = Y + 10*c1  ->  7 + 5 = 2 + 10*1`.
+fn column_arithmetic(column_idx: usize, batch: &InputBatch, carries: &[i32], mapping: &Mapping) -> Vec<String> {
+    let base = batch.base as i32;
+    let equations_count = batch.equations.len();
+    batch.equations.iter()
+        .enumerate()
+        .map(|(equation_idx, equation)| {
+            let arithmetic = if equation.is_linear {
+                let (letters, constant) = &equation.columns[column_idx];
+                let carry_in = carries[column_idx * equations_count + equation_idx];
+                let total = carry_in + constant + letters.iter().map(|&(letter, coefficient)| coefficient * mapping[letter] as i32).sum::<i32>();
+                let holds = total.rem_euclid(base) == 0;
 
-    let mut required_chars: HashSet<char> = column.0.keys().copied().collect();
-    required_chars.insert(column.1);
+                // Each term as written with letters and with digits, left side terms are added and right side ones subtracted
+                let mut terms: Vec<(i32, String, String)> = letters.iter()
+                    .filter(|&&(_, coefficient)| coefficient != 0)
+                    .map(|&(letter, coefficient)| {
+                        let (ch, digit) = (batch.unique_chars[letter], mapping[letter]);
+                        match coefficient.abs() {
+                            1 => (coefficient, ch.to_string(), digit.to_string()),
+                            times => (coefficient, format!("{}*{}", times, ch), format!("{}*{}", times, digit)),
+                        }
+                    })
+                    .collect();
+                if *constant != 0 {
+                    terms.push((*constant, constant.abs().to_string(), constant.abs().to_string()));
+                }
+                if column_idx > 0 {
+                    terms.push((1, format!("c{}", column_idx), carry_in.to_string()));
+                }
+                let carry_out = if holds { format!("{}*{}", base, total / base) } else { format!("{}*c{}", base, column_idx + 1) };
+                terms.push((-1, format!("{}*c{}", base, column_idx + 1), carry_out));
 
-    let not_mapped_chars: HashSet<char> = required_chars.iter().copied().filter(|key| mapping.get(key).unwrap().is_none()).collect();
-    let not_mappedchars_count = not_mapped_chars.len();
+                let side = |positive: bool, with_digits: bool| {
+                    let side_terms: Vec<&str> = terms.iter()
+                        .filter(|(sign, _, _)| (*sign > 0) == positive)
+                        .map(|(_, letters, digits)| if with_digits { digits.as_str() } else { letters.as_str() })
+                        .collect();
+                    if side_terms.is_empty() { "0".to_string() } else { side_terms.join(" + ") }
+                };
+                let arithmetic = format!("{} = {}  ->  {} = {}", side(true, false), side(false, false), side(true, true), side(false, true));
+                if holds { arithmetic } else { format!("{} has no integer c{}", arithmetic, column_idx + 1) }
+            } else if apply_mapping_modulo(column_idx, equation, batch.base, mapping) {
+                format!("product matches modulo {}^{}", batch.base, column_idx + 1)
+            } else {
+                format!("product does not match modulo {}^{}", batch.base, column_idx + 1)
+            };
+            if equations_count > 1 { format!("equation {}: {}", equation_idx + 1, arithmetic) } else { arithmetic }
+        })
+        .collect()
+}
 
-    // Nothing to map, no more permutations needed - just check
-    if not_mappedchars_count == 0 {
-        if let Some(value) = build_result(column_idx, batch, last_remainder, &mapping, column) {
-            return Some(value);
-        }
+fn build_result(column_idx: usize, batch: &InputBatch, state: &mut State) -> bool {
+    if !check_column(column_idx, batch, &mut state.carries, &state.mapping) {
+        state.stats.pruned_column_mismatch += 1;
+        state.record(|state| TraceEvent::Backtrack {
+            column: column_idx,
+            reason: BacktrackReason::ColumnMismatch { arithmetic: column_arithmetic(column_idx, batch, &state.carries, &state.mapping) },
+        });
+        return false;
     }
+    state.record(|state| TraceEvent::ColumnHolds { column: column_idx, arithmetic: column_arithmetic(column_idx, batch, &state.carries, &state.mapping) });
 
-    // Drop already mapped
-    let possible_values: Vec<_> = (0..10u8).filter(|&v| !mapping.values().contains(&Some(v))).collect();
+    // Seems valid
+    if column_idx == batch.columns_count - 1 {
+        state.stats.permutations += 1;
+        let valid = check_last_column(batch, &state.carries, &state.mapping);
+        if !valid {
+            state.stats.pruned_column_mismatch += 1;
+            state.record(|state| {
+                let carries = state.carries[batch.columns_count * batch.equations.len()..].to_vec();
+                let reason = if carries.iter().any(|&carry| carry != 0) {
+                    BacktrackReason::CarryLeft { carries }
+                } else {
+                    BacktrackReason::ProductMismatch
+                };
+                TraceEvent::Backtrack { column: column_idx, reason }
+            });
+        }
+        valid
+    } else {
+        find_column_mapping(column_idx + 1, batch, state)
+    }
+}
 
-    // Start finding mapping for yet not mapped chars
-    for permutation in possible_values.iter().permutations(not_mappedchars_count) {
+fn next_letter(column_idx: usize, batch: &InputBatch, state: &State) -> Option<usize> {
+    // Letters mapped in previous columns keep their digits
+    let mut not_mapped = batch.columns_letters[column_idx].iter()
+        .copied()
+        .filter(|&letter| state.mapping[letter] == UNMAPPED);
+    match batch.heuristic {
+        Heuristic::MinimumRemainingValues => not_mapped.min_by_key(|&letter| (batch.domains[letter] & !state.used).count_ones()),
+        _ => not_mapped.next(),
+    }
+}
 
-        // Update mapping
-        let update_iter = not_mapped_chars.iter()
-            .copied()
-            .zip(permutation.into_iter());
-        for (key, &value) in update_iter {
-            *mapping.get_mut(&key).unwrap() = Some(value);
+fn find_chars_mapping(column_idx: usize, batch: &InputBatch, state: &mut State) -> bool {
+    let Some(letter) = next_letter(column_idx, batch, state) else {
+        // Every char of the column mapped - validate and go to the next column
+        if !check_no_leftside_zeros(batch, &state.mapping) {
+            state.stats.pruned_leading_zero += 1;
+            state.record(|state| {
+                let symbol = batch.leading.iter().find(|symbol| symbol.value(&state.mapping) == 0).unwrap();
+                TraceEvent::Backtrack { column: column_idx, reason: BacktrackReason::LeadingZero { letter: symbol_char(*symbol, batch) } }
+            });
+            return false;
         }
+        return build_result(column_idx, batch, state);
+    };
 
-        // Fast validate new mapping
-        if !check_no_leftside_zeros(batch, &mapping) {
-            continue;
-        } 
+    // Draw only values allowed for the char, dropping already mapped
+    let mut digits = batch.domains[letter] & !state.used;
+    state.path.push((0, digits.count_ones()));
+    let mut digit_idx = 0;
+    while digits != 0 {
+        let digit = digits.trailing_zeros() as u8;
+        digits &= digits - 1;
 
-        // Find solution with recently updated mapping
-        if let Some(value) = build_result(column_idx, batch, last_remainder, &mapping, column) {
-            return Some(value);
+        state.mapping[letter] = digit;
+        state.used |= 1 << digit;
+        state.stats.nodes += 1;
+        state.path.last_mut().unwrap().0 = digit_idx;
+        digit_idx += 1;
+        state.reporter.tick(state.stats.nodes, 0, || explored_fraction(&state.path));
+        state.record(|_| TraceEvent::Assign { column: column_idx, letter: batch.unique_chars[letter], digit });
+        if state.limits.exceeds_nodes(state.stats.nodes) {
+            state.aborted = Some(AbortReason::NodeLimit);
+            return false;
+        }
+        if find_chars_mapping(column_idx, batch, state) {
+            return true;
         }
+        if state.aborted.is_some() {
+            return false;
+        }
+        state.used &= !(1 << digit);
+    }
+    state.mapping[letter] = UNMAPPED;
+    state.path.pop();
+    state.record(|_| TraceEvent::Backtrack { column: column_idx, reason: BacktrackReason::DigitsExhausted { letter: batch.unique_chars[letter] } });
+
+    false
+}
+
+fn find_column_mapping(column_idx: usize, batch: &InputBatch, state: &mut State) -> bool {
+    state.stats.max_depth = state.stats.max_depth.max(column_idx + 1);
+    state.aborted = state.limits.check(state.stats.nodes);
+    if state.aborted.is_some() {
+        return false;
+    }
+    state.record(|state| {
+        let equations_count = batch.equations.len();
+        TraceEvent::EnterColumn { column: column_idx, carries_in: state.carries[column_idx * equations_count..(column_idx + 1) * equations_count].to_vec() }
+    });
+    find_chars_mapping(column_idx, batch, state)
+}
+
+pub fn solve(input: &str) -> Option<Solution> {
+    solve_puzzle(&Puzzle::parse(input).ok()?)
+}
+
+pub fn solve_puzzle(puzzle: &Puzzle) -> Option<Solution> {
+    solve_with(puzzle, &SolveOptions::default())
+}
+
+pub fn solve_with(puzzle: &Puzzle, options: &SolveOptions) -> Option<Solution> {
+    solve_with_stats(puzzle, options).0
+}
+
+pub fn solve_with_stats(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, SolveStats) {
+    let (result, stats, _) = search(puzzle, options, &Limits::default(), false);
+    (result.ok().flatten(), stats)
+}
+
+/// Same as `solve_with`, but the search stops once any of the limits is reached.
+pub fn solve_limited(puzzle: &Puzzle, options: &SolveOptions, limits: &Limits) -> SolveOutcome {
+    match search(puzzle, options, limits, false) {
+        (Ok(Some(solution)), _, _) => SolveOutcome::Solved(solution),
+        (Ok(None), _, _) => SolveOutcome::NoSolution,
+        (Err(reason), partial_stats, _) => SolveOutcome::Aborted { reason, partial_stats },
     }
+}
+
+type SearchResult = Result<Option<Solution>, AbortReason>;
 
-    None
+/// Same as `solve_with`, with every column visited, digit drawn and backtrack recorded on the way.
+pub fn solve_traced(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, Trace) {
+    let (result, _, trace) = search(puzzle, options, &Limits::default(), true);
+    (result.ok().flatten(), trace)
 }
 
-pub fn solve(input: &str) -> Option<HashMap<char, u8>> {
-    let batch = InputBatch::from_str(input);
-    let result = find_column_mapping(
-        0,
-        &batch,
-        0,
-        HashMap::from_iter(batch.unique_chars.iter().copied().zip((0..batch.unique_chars.len()).map(|_| None)))
-    );
-    result.map(|r| r.into_iter()
-        .map(|(k, v)| (k, v.unwrap())).collect())
+fn search(puzzle: &Puzzle, options: &SolveOptions, limits: &Limits, traced: bool) -> (SearchResult, SolveStats, Trace) {
+    let start = Instant::now();
+    let batch = InputBatch::from_puzzle(puzzle, options);
+    let mut state = State {
+        mapping: [UNMAPPED; MAX_LETTERS],
+        used: 0,
+        carries: vec![0; (batch.columns_count + 1) * batch.equations.len()],
+        stats: SolveStats::default(),
+        limits: limits.clone(),
+        aborted: None,
+        reporter: ProgressReporter::new(limits.observer()),
+        path: Vec::with_capacity(MAX_LETTERS),
+        trace: traced.then(Trace::default),
+    };
+
+    // Letters with a single allowed digit are bound upfront
+    for (letter, &digits) in batch.domains.iter().enumerate().filter(|(_, digits)| digits.count_ones() == 1) {
+        if state.used & digits != 0 {
+            state.stats.elapsed = start.elapsed();
+            return (Ok(None), state.stats, state.trace.unwrap_or_default());
+        }
+        state.mapping[letter] = digits.trailing_zeros() as u8;
+        state.used |= digits;
+    }
+
+    let solution = find_column_mapping(0, &batch, &mut state).then(|| batch.result(&state.mapping));
+    state.stats.elapsed = start.elapsed();
+    if state.aborted.is_none() {
+        state.reporter.finish(state.stats.nodes, solution.iter().count(), 1.0);
+    }
+    if solution.is_some() {
+        state.record(|_| TraceEvent::Solved);
+    }
+    let trace = state.trace.unwrap_or_default();
+    match state.aborted {
+        Some(reason) => (Err(reason), state.stats, trace),
+        None => (Ok(solution), state.stats, trace),
+    }
 }
 
 #[test]
@@ -266,6 +667,487 @@ fn puzzle_with_ten_letters() {
     assert_eq!(answer, Some(expected));
 }
 
+#[test]
+fn addend_longer_than_result_is_invalid() {
+    let answer = solve("AB + A == C");
+    assert_eq!(answer, None);
+}
+
+#[test]
+fn carry_out_of_last_column_is_invalid() {
+    let answer = solve("A + A == B");
+    let expected = [('A', 1), ('B', 2)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+
+    // Every column matches, only the carry left over makes it unsolvable
+    let puzzle = Puzzle::parse("A + B == C").unwrap();
+    let options = SolveOptions::new().domain('A', 5..10).domain('B', 5..10);
+    assert_eq!(solve_with(&puzzle, &options), None);
+}
+
+#[test]
+fn product_puzzle_with_three_letters() {
+    let answer = solve("NO * NO == ONE");
+    let expected = [('N', 2), ('O', 7), ('E', 9)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn product_puzzle_with_six_letters() {
+    let answer = solve("ABC * ABC == DEFABC");
+    let expected = [('A', 6), ('B', 2), ('C', 5), ('D', 3), ('E', 9), ('F', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn product_puzzle_with_nine_letters() {
+    // 807 * 807 == 651249, 846 * 846 == 715716 and 576 * 576 == 331776 are rejected by repeated digits
+    let answer = solve("TWO * TWO == SQUARE").unwrap();
+    let value = |word: &str| word.chars().fold(0u64, |acc, ch| acc * 10 + answer[&ch] as u64);
+    assert_eq!(value("TWO") * value("TWO"), value("SQUARE"));
+}
+
+#[test]
+fn product_and_sum_puzzle() {
+    let answer = solve("AB * C + D == EF").unwrap();
+    let value = |word: &str| word.chars().fold(0u64, |acc, ch| acc * 10 + answer[&ch] as u64);
+    assert_eq!(value("AB") * value("C") + value("D"), value("EF"));
+}
+
+#[test]
+fn system_of_three_equations() {
+    let answer = solve("A + A == B, B + B == CD, D + D == E");
+    let expected = [('A', 3), ('B', 6), ('C', 1), ('D', 2), ('E', 4)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn system_of_equations_on_separate_lines() {
+    let answer = solve("A + A + A == B\nB + B == CD\n");
+    let expected = [('A', 3), ('B', 9), ('C', 1), ('D', 8)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn system_without_common_solution() {
+    let answer = solve("A + A == B, B + B == A");
+    assert_eq!(answer, None);
+}
+
+#[test]
+fn puzzle_with_difference() {
+    let answer = solve("MONEY - MORE == SEND");
+    let expected = [
+        ('S', 9),
+        ('E', 5),
+        ('N', 6),
+        ('D', 7),
+        ('M', 1),
+        ('O', 0),
+        ('R', 8),
+        ('Y', 2),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn system_with_ten_letters() {
+    let answer = solve("AB + CD == EF, EF - GH == IJ").unwrap();
+    let value = |word: &str| word.chars().fold(0i64, |acc, ch| acc * 10 + answer[&ch] as i64);
+    assert_eq!(answer.values().unique().count(), 10);
+    assert_eq!(value("AB") + value("CD"), value("EF"));
+    assert_eq!(value("EF") - value("GH"), value("IJ"));
+}
+
+#[test]
+fn puzzle_with_words_on_both_sides() {
+    let answer = solve("ABC + ABC == DB + DB + DB + D");
+    let expected = [('A', 1), ('B', 2), ('C', 7), ('D', 8)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn puzzle_with_single_equality_sign() {
+    let answer = solve("AS + A = MOM");
+    let expected = [('A', 9), ('S', 2), ('M', 1), ('O', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn puzzle_with_sums_on_both_sides() {
+    let answer = solve("SEND + MORE + A == MONEY + B").unwrap();
+    let value = |word: &str| word.chars().fold(0i64, |acc, ch| acc * 10 + answer[&ch] as i64);
+    assert_eq!(answer.values().unique().count(), 10);
+    assert_eq!(value("SEND") + value("MORE") + value("A"), value("MONEY") + value("B"));
+}
+
+#[test]
+fn puzzle_in_base_8() {
+    let puzzle = Puzzle::parse_with("AS + A == MOM", &ParseOptions::new().base(8)).unwrap();
+    let expected = [('A', 7), ('S', 2), ('M', 1), ('O', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_in_base_12() {
+    let puzzle = Puzzle::parse_with("A + A + A + A + A + A + A + A + A + A + A + A + A + B == BCC", &ParseOptions::new().base(12)).unwrap();
+    let expected = [('A', 11), ('B', 1), ('C', 0)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_with_ten_letters_in_base_12() {
+    let puzzle = Puzzle::parse_with("SEND + MORE + GOLD == MONEY", &ParseOptions::new().base(12)).unwrap();
+    let answer = solve_puzzle(&puzzle).unwrap();
+    let value = |word: &str| word.chars().fold(0i64, |acc, ch| acc * 12 + answer[&ch] as i64);
+    assert_eq!(answer.values().unique().count(), 10);
+    assert_eq!(value("SEND") + value("MORE") + value("GOLD"), value("MONEY"));
+}
+
+#[test]
+fn puzzle_with_eight_letters_in_base_16() {
+    let puzzle = Puzzle::parse_with("SEND + MORE == MONEY", &ParseOptions::new().base(16)).unwrap();
+    let answer = solve_puzzle(&puzzle).unwrap();
+    let value = |word: &str| word.chars().fold(0i64, |acc, ch| acc * 16 + answer[&ch] as i64);
+    assert_eq!(answer.values().unique().count(), 8);
+    assert!(answer.values().all(|&digit| digit < 16));
+    assert_eq!(value("SEND") + value("MORE"), value("MONEY"));
+}
+
+#[test]
+fn puzzle_without_solution_in_base_8() {
+    let puzzle = Puzzle::parse_with("SEND + MORE == MONEY", &ParseOptions::new().base(8)).unwrap();
+    assert_eq!(solve_puzzle(&puzzle), None);
+}
+
+#[test]
+fn product_puzzle_in_base_16() {
+    let puzzle = Puzzle::parse_with("TWO * TWO == SQUARE", &ParseOptions::new().base(16)).unwrap();
+    let answer = solve_puzzle(&puzzle).unwrap();
+    let value = |word: &str| word.chars().fold(0i64, |acc, ch| acc * 16 + answer[&ch] as i64);
+    assert_eq!(value("TWO") * value("TWO"), value("SQUARE"));
+}
+
+#[test]
+fn puzzle_with_lowercase_letters() {
+    let puzzle = Puzzle::parse_with("as + A == mom", &ParseOptions::new().case_insensitive(true)).unwrap();
+    let expected = [('A', 9), ('S', 2), ('M', 1), ('O', 0)]
+        .into_iter()
+        .collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_with_non_ascii_letters() {
+    let puzzle = Puzzle::parse_with("σενδ + μορε == μονευ", &ParseOptions::new().case_insensitive(true)).unwrap();
+    let expected = [
+        ('Σ', 9),
+        ('Ε', 5),
+        ('Ν', 6),
+        ('Δ', 7),
+        ('Μ', 1),
+        ('Ο', 0),
+        ('Ρ', 8),
+        ('Υ', 2),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_with_literal_digits() {
+    let puzzle = Puzzle::parse_with("I + B9 == 1LL", &ParseOptions::new().literal_digits(true)).unwrap();
+    let expected = [('I', 1), ('B', 9), ('L', 0)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+}
+
+#[test]
+fn puzzle_with_literal_digits_inside_words() {
+    let puzzle = Puzzle::parse_with("A1B + C == 2DE", &ParseOptions::new().literal_digits(true)).unwrap();
+    let answer = solve_puzzle(&puzzle).unwrap();
+    let value = |word: &str| word.chars().fold(0i64, |acc, ch| acc * 10 + ch.to_digit(10).map_or_else(|| answer[&ch] as i64, |digit| digit as i64));
+    assert_eq!(answer.len(), 5);
+    assert_eq!(value("A1B") + value("C"), value("2DE"));
+}
+
+#[test]
+fn literal_leading_zero_is_invalid() {
+    let puzzle = Puzzle::parse_with("0A + B == C", &ParseOptions::new().literal_digits(true)).unwrap();
+    assert_eq!(solve_puzzle(&puzzle), None);
+}
+
+#[test]
+fn puzzle_with_fixed_letter() {
+    let puzzle = Puzzle::parse("TWO * TWO == SQUARE").unwrap();
+    let expected = [
+        ('T', 8),
+        ('W', 0),
+        ('O', 7),
+        ('S', 6),
+        ('Q', 5),
+        ('U', 1),
+        ('A', 2),
+        ('R', 4),
+        ('E', 9),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('W', 0)), Some(expected));
+}
+
+#[test]
+fn puzzle_with_restricted_domains() {
+    let puzzle = Puzzle::parse("TWO * TWO == SQUARE").unwrap();
+    let options = SolveOptions::new()
+        .domain('S', [1, 3, 5, 7, 9])
+        .domain('T', [8, 9]);
+    let expected = [
+        ('T', 8),
+        ('W', 5),
+        ('O', 4),
+        ('S', 7),
+        ('Q', 2),
+        ('U', 9),
+        ('A', 3),
+        ('R', 1),
+        ('E', 6),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(solve_with(&puzzle, &options), Some(expected));
+}
+
+#[test]
+fn contradicting_hints_have_no_solution() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('E', 4)), None);
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('E', 5).fix('N', 5)), None);
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().domain('M', [2, 3])), None);
+}
+
+#[test]
+fn hints_for_letters_missing_in_puzzle_are_ignored() {
+    let puzzle = Puzzle::parse("I + BB == ILL").unwrap();
+    let expected = [('I', 1), ('B', 9), ('L', 0)].into_iter().collect();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().fix('X', 1)), Some(expected));
+}
+
+#[test]
+fn leading_zeros_policies() {
+    let puzzle = Puzzle::parse("ACA + DD == BD").unwrap();
+    assert_eq!(solve_puzzle(&puzzle), None);
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::SingleLetter)), None);
+    let answer = solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::Allowed)).unwrap();
+    assert_eq!(answer[&'A'], 0);
+    assert_eq!(answer[&'C'] + answer[&'D'], answer[&'B']);
+}
+
+#[test]
+fn single_letter_word_may_be_zero() {
+    let puzzle = Puzzle::parse("SEND + MORE + O == MONEY").unwrap();
+    assert_eq!(solve_puzzle(&puzzle), None);
+    let expected = [
+        ('S', 9),
+        ('E', 5),
+        ('N', 6),
+        ('D', 7),
+        ('M', 1),
+        ('O', 0),
+        ('R', 8),
+        ('Y', 2),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::SingleLetter)), Some(expected));
+
+    let answer = solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::Allowed)).unwrap();
+    let value = |word: &str| word.chars().fold(0i64, |acc, ch| acc * 10 + answer[&ch] as i64);
+    assert_eq!(value("SEND") + value("MORE") + value("O"), value("MONEY"));
+}
+
+#[test]
+fn literal_leading_zero_with_allowed_leading_zeros() {
+    let puzzle = Puzzle::parse_with("0A + B == C", &ParseOptions::new().literal_digits(true)).unwrap();
+    let answer = solve_with(&puzzle, &SolveOptions::new().leading_zeros(LeadingZeros::Allowed)).unwrap();
+    assert_eq!(answer[&'A'] + answer[&'B'], answer[&'C']);
+}
+
+#[test]
+fn first_solution_follows_letter_order() {
+    let puzzle = Puzzle::parse("C + B == A").unwrap();
+    let expected = [('C', 1), ('B', 2), ('A', 3)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+
+    let options = SolveOptions::new().letter_order(LetterOrder::Alphabetical);
+    let expected = [('A', 3), ('B', 1), ('C', 2)].into_iter().collect();
+    assert_eq!(solve_with(&puzzle, &options), Some(expected));
+}
+
+#[test]
+fn first_solution_is_reproducible() {
+    for order in [LetterOrder::FirstAppearance, LetterOrder::Alphabetical, LetterOrder::MostSignificantFirst, LetterOrder::MostFrequentFirst] {
+        let options = SolveOptions::new().letter_order(order);
+        let first = solve_with(&Puzzle::parse("AB + BA == CDC").unwrap(), &options);
+        assert!(first.is_some());
+        for _ in 0..10 {
+            assert_eq!(solve_with(&Puzzle::parse("AB + BA == CDC").unwrap(), &options), first);
+        }
+    }
+}
+
+#[test]
+fn heuristics_find_same_solutions() {
+    // Digits drawn by Static, MinimumRemainingValues, LeadingFirst and LargestCoefficient
+    let inputs = [
+        ("I + BB == ILL", [66, 66, 66, 66]),
+        ("ACA + DD == BD", [81, 81, 81, 81]),
+        ("A + A + A + A + A + A + A + A + A + A + A + B == BCC", [587, 587, 587, 587]),
+        ("NO + NO + TOO == LATE", [318, 318, 318, 318]),
+        ("HE + SEES + THE == LIGHT", [635, 1180, 1180, 635]),
+        ("SEND + MORE == MONEY", [4175, 4175, 4175, 4175]),
+        ("AND + A + STRONG + OFFENSE + AS + A + GOOD == DEFENSE", [28299, 27641, 27641, 28299]),
+        ("ABC * ABC == DEFABC", [48, 48, 48, 48]),
+        ("A + A == B, B + B == CD, D + D == E", [1025, 1025, 1025, 2088]),
+    ];
+    let heuristics = [Heuristic::Static, Heuristic::MinimumRemainingValues, Heuristic::LeadingFirst, Heuristic::LargestCoefficient];
+
+    for (input, expected_nodes) in inputs {
+        let puzzle = Puzzle::parse(input).unwrap();
+        let expected = solve_puzzle(&puzzle);
+        let nodes = heuristics.map(|heuristic| {
+            let (solution, stats) = solve_with_stats(&puzzle, &SolveOptions::new().heuristic(heuristic));
+            assert_eq!(solution, expected, "{} {:?}", input, heuristic);
+            stats.nodes
+        });
+        assert_eq!(nodes, expected_nodes, "{}", input);
+    }
+}
+
+#[test]
+fn stats_of_search() {
+    let (answer, stats) = solve_with_stats(&Puzzle::parse("SEND + MORE == MONEY").unwrap(), &SolveOptions::default());
+    assert!(answer.is_some());
+    assert_eq!(stats.max_depth, 5);
+    assert_eq!(stats.permutations, 1);
+    assert!(stats.nodes > 0);
+    assert!(stats.pruned_column_mismatch > 0);
+
+    let (answer, stats) = solve_with_stats(&Puzzle::parse_with("A + B == 0C", &ParseOptions::new().literal_digits(true)).unwrap(), &SolveOptions::default());
+    assert_eq!(answer, None);
+    assert!(stats.pruned_leading_zero > 0);
+    assert_eq!(stats.permutations, 0);
+}
+
+#[test]
+fn search_within_limits() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let expected = solve_puzzle(&puzzle).unwrap();
+    assert_eq!(solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().max_nodes(1_000_000)), SolveOutcome::Solved(expected));
+    assert_eq!(solve_limited(&Puzzle::parse("ACA + DD == BD").unwrap(), &SolveOptions::default(), &Limits::new()), SolveOutcome::NoSolution);
+}
+
+#[test]
+fn search_aborted_by_limits() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let SolveOutcome::Aborted { reason, partial_stats } = solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().max_nodes(100)) else {
+        panic!("search should be aborted");
+    };
+    assert_eq!(reason, AbortReason::NodeLimit);
+    assert_eq!(partial_stats.nodes, 101);
+
+    let outcome = solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().timeout(Duration::ZERO));
+    assert!(matches!(outcome, SolveOutcome::Aborted { reason: AbortReason::Deadline, .. }));
+
+    let token = CancelToken::new();
+    token.cancel();
+    let outcome = solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().cancel_token(token));
+    assert!(matches!(outcome, SolveOutcome::Aborted { reason: AbortReason::Cancelled, .. }));
+}
+
+#[test]
+fn explored_fraction_of_path() {
+    assert_eq!(explored_fraction(&[]), 0.0);
+    assert_eq!(explored_fraction(&[(1, 2)]), 0.5);
+    assert_eq!(explored_fraction(&[(1, 2), (2, 4)]), 0.75);
+    assert_eq!(explored_fraction(&[(3, 4), (1, 2)]), 0.875);
+}
+
+#[test]
+fn progress_is_reported() {
+    let reports = Arc::new(Mutex::new(vec![]));
+    let observer = {
+        let reports = reports.clone();
+        ProgressObserver::new(Duration::ZERO, move |progress: &Progress| reports.lock().unwrap().push(progress.clone()))
+    };
+    let puzzle = Puzzle::parse("AND + A + STRONG + OFFENSE + AS + A + GOOD == DEFENSE").unwrap();
+    let outcome = solve_limited(&puzzle, &SolveOptions::default(), &Limits::new().progress(observer));
+    assert!(matches!(outcome, SolveOutcome::Solved(_)));
+
+    let reports = reports.lock().unwrap();
+    assert!(reports.len() > 10);
+    assert!(reports.windows(2).all(|pair| pair[0].explored <= pair[1].explored && pair[0].nodes < pair[1].nodes));
+    let last = reports.last().unwrap();
+    assert_eq!((last.explored, last.solutions), (1.0, 1));
+}
+
+#[test]
+fn trace_of_solution() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let (answer, trace) = solve_traced(&puzzle, &SolveOptions::default());
+    assert_eq!(answer, solve_puzzle(&puzzle));
+    assert_eq!(trace.events.last(), Some(&TraceEvent::Solved));
+    assert_eq!(trace.events[0], TraceEvent::EnterColumn { column: 0, carries_in: vec![0] });
+
+    // Columns of the solution, from the last time every column held
+    let holds: Vec<&TraceEvent> = (0..5)
+        .map(|column| trace.events.iter().rev().find(|event| matches!(event, TraceEvent::ColumnHolds { column: c, .. } if *c == column)).unwrap())
+        .collect();
+    assert_eq!(holds[0], &TraceEvent::ColumnHolds { column: 0, arithmetic: vec!["D + E = Y + 10*c1  ->  7 + 5 = 2 + 10*1".to_string()] });
+    assert_eq!(holds[4], &TraceEvent::ColumnHolds { column: 4, arithmetic: vec!["c4 = M + 10*c5  ->  1 = 1 + 10*0".to_string()] });
+
+    let text = trace.to_string();
+    assert!(text.contains("  try D = 7\n"));
+    assert!(text.contains("  backtrack: "));
+    assert!(text.ends_with("solved\n"));
+}
+
+#[test]
+fn trace_of_backtracks() {
+    let (answer, trace) = solve_traced(&Puzzle::parse("AB + A == C").unwrap(), &SolveOptions::default());
+    assert_eq!(answer, None);
+    assert!(trace.events.iter().any(|event| matches!(event, TraceEvent::Backtrack { reason: BacktrackReason::ColumnMismatch { .. }, .. })));
+    assert!(trace.events.iter().any(|event| matches!(event, TraceEvent::Backtrack { reason: BacktrackReason::DigitsExhausted { letter: 'B' }, .. })));
+
+    let (_, trace) = solve_traced(&Puzzle::parse("AB + CD == E").unwrap(), &SolveOptions::default());
+    assert!(trace.events.iter().any(|event| matches!(event, TraceEvent::Backtrack { reason: BacktrackReason::CarryLeft { .. }, .. })));
+
+    let (_, trace) = solve_traced(&Puzzle::parse_with("A + B == 0C", &ParseOptions::new().literal_digits(true)).unwrap(), &SolveOptions::default());
+    assert!(trace.events.iter().any(|event| matches!(event, TraceEvent::Backtrack { reason: BacktrackReason::LeadingZero { letter: '0' }, .. })));
+}
+
+#[test]
+fn words_longer_than_u64_digits() {
+    let input = format!("{} + {} == {}", "AB".repeat(20), "BA".repeat(20), "C".repeat(40));
+    let expected = [('A', 1), ('B', 2), ('C', 3)].into_iter().collect();
+    assert_eq!(solve(&input), Some(expected));
+
+    // Product of 40 digits is only checked exactly once every letter has a digit
+    let input = format!("{} * B == {}", "A".repeat(40), "C".repeat(40));
+    let answer = solve(&input).unwrap();
+    assert_eq!(answer, [('A', 2), ('B', 3), ('C', 6)].into_iter().collect());
+    assert_eq!(crate::verify::verify(&input, &answer.to_map()), Ok(()));
+    assert_eq!(solve(&format!("{} * B == {}", "A".repeat(40), "C".repeat(41))), None);
+}
+
 #[test]
 fn puzzle_with_ten_letters_and_199_addends() {
     let answer = solve("THIS + A + FIRE + THEREFORE + FOR + ALL + HISTORIES + I + TELL + A + TALE + THAT + FALSIFIES + ITS + TITLE + TIS + A + LIE + THE + TALE + OF + THE + LAST + FIRE + HORSES + LATE + AFTER + THE + FIRST + FATHERS + FORESEE + THE + HORRORS + THE + LAST + FREE + TROLL + TERRIFIES + THE + HORSES + OF + FIRE + THE + TROLL + RESTS + AT + THE + HOLE + OF + LOSSES + IT + IS + THERE + THAT + SHE + STORES + ROLES + OF + LEATHERS + AFTER + SHE + SATISFIES + HER + HATE + OFF + THOSE + FEARS + A + TASTE + RISES + AS + SHE + HEARS + THE + LEAST + FAR + HORSE + THOSE + FAST + HORSES + THAT + FIRST + HEAR + THE + TROLL + FLEE + OFF + TO + THE + FOREST + THE + HORSES + THAT + ALERTS + RAISE + THE + STARES + OF + THE + OTHERS + AS + THE + TROLL + ASSAILS + AT + THE + TOTAL + SHIFT + HER + TEETH + TEAR + HOOF + OFF + TORSO + AS + THE + LAST + HORSE + FORFEITS + ITS + LIFE + THE + FIRST + FATHERS + HEAR + OF + THE + HORRORS + THEIR + FEARS + THAT + THE + FIRES + FOR + THEIR + FEASTS + ARREST + AS + THE + FIRST + FATHERS + RESETTLE + THE + LAST + OF + THE + FIRE + HORSES + THE + LAST + TROLL + HARASSES + THE + FOREST + HEART + FREE + AT + LAST + OF + THE + LAST + TROLL + ALL + OFFER + THEIR + FIRE + HEAT + TO + THE + ASSISTERS + FAR + OFF + THE + TROLL + FASTS + ITS + LIFE + SHORTER + AS + STARS + RISE + THE + HORSES + REST + SAFE + AFTER + ALL + SHARE + HOT + FISH + AS + THEIR + AFFILIATES + TAILOR + A + ROOFS + FOR + THEIR + SAFE == FORTRESSES");
@@ -284,4 +1166,12 @@ fn puzzle_with_ten_letters_and_199_addends() {
     .into_iter()
     .collect();
     assert_eq!(answer, Some(expected));
-}
\ No newline at end of file
+}
+
+#[test]
+fn long_product_in_base_19() {
+    // Remainders of the 15 lowest digits in base 19 fit in u64 but their product does not fit in i128
+    let puzzle = Puzzle::parse_with("SRQPONMLKJIHGFE * SRQPONMLKJIHGFE == SQONPAJEFONCBMRLJKNSDGHERFFSCQ", &ParseOptions::new().base(19)).unwrap();
+    let solution = solve_puzzle(&puzzle).unwrap();
+    assert!(solution.verify(&puzzle));
+}
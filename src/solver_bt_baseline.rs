@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use itertools::Itertools;
+
+use crate::options::SolveOptions;
+use crate::puzzle::{literal_digit, Equation, Puzzle, Term};
+
+/// Signed count of every letter in a column, letters of the right side count as negative,
+/// and the signed sum of literal digits of the column.
+type Column = (HashMap<char, i32>, i32);
+type Mapping = HashMap<char, Option<u8>>;
+
+/// Single equation of the puzzle with words reversed, so index of a char is its column.
+/// Terms of the right side are moved to the left, so all terms sum up to zero.
+#[derive(Debug)]
+struct EquationBatch {
+    terms: Vec<Term>,
+    is_linear: bool,
+    columns: Vec<Column>,
+}
+
+impl EquationBatch {
+    fn from_equation(equation: &Equation, columns_count: usize) -> Self {
+        let terms: Vec<Term> = equation.signed_terms()
+            .map(|(negative, term)| Term {
+                negative,
+                factors: term.factors.iter().map(|word| word.chars().rev().collect()).collect(),
+            })
+            .collect();
+
+        // Columns with letters coefficients only describe sums and differences, products are checked modulo power of 10
+        let columns: Vec<_> = if equation.is_linear() {
+            (0..columns_count)
+                .map(|idx| terms.iter()
+                    .filter_map(|term| term.factors[0].chars().nth(idx).map(|ch| (ch, if term.negative { -1 } else { 1 })))
+                    .fold((HashMap::new(), 0i32), |mut acc, (ch, coefficient)| {
+                        match literal_digit(ch) {
+                            Some(digit) => acc.1 += coefficient * digit as i32,
+                            None => *acc.0.entry(ch).or_insert(0i32) += coefficient,
+                        }
+                        acc
+                    }))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Self {
+            terms,
+            is_linear: equation.is_linear(),
+            columns,
+        }
+    }
+
+    fn words(&self) -> impl Iterator<Item = &String> {
+        self.terms.iter().flat_map(|term| term.factors.iter())
+    }
+}
+
+#[derive(Debug)]
+struct InputBatch {
+    unique_chars: Vec<char>,
+    lines: Vec<String>,
+    equations: Vec<EquationBatch>,
+    columns_count: usize,
+    columns_chars: Vec<Vec<char>>,
+    domains: HashMap<char, Vec<u8>>,
+    base: u8,
+}
+
+impl InputBatch {
+    fn from_puzzle(puzzle: &Puzzle, options: &SolveOptions) -> Self {
+        let columns_count = puzzle.equations.iter()
+            .flat_map(|equation| equation.words())
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap();
+
+        // Every equation is padded to the same columns count, so constraints of all of them are checked together
+        let equations: Vec<_> = puzzle.equations.iter()
+            .map(|equation| EquationBatch::from_equation(equation, columns_count))
+            .collect();
+
+        // Only words which may not start with zero are kept
+        let lines = equations.iter()
+            .flat_map(|equation| equation.words())
+            .filter(|word| options.forbids_leading_zero(word))
+            .cloned()
+            .collect();
+
+        let columns_chars = (0..columns_count)
+            .map(|idx| equations.iter()
+                .flat_map(|equation| equation.words())
+                .filter_map(|s| s.chars().nth(idx))
+                .filter(|&ch| literal_digit(ch).is_none())
+                .unique()
+                .collect())
+            .collect();
+
+        let domains = puzzle.unique_chars.iter()
+            .map(|&ch| (ch, (0..puzzle.base).filter(|&digit| options.allows(ch, digit)).collect()))
+            .collect();
+
+        Self {
+            unique_chars: puzzle.unique_chars.clone(),
+            lines,
+            equations,
+            columns_count,
+            columns_chars,
+            domains,
+            base: puzzle.base,
+        }
+    }
+}
+
+fn apply_mapping_to_column(last_remainder: i32, col: &Column, base: u8, mapping: &Mapping) -> Option<i32> {
+    let base = base as i32;
+    let column_value: i32 = last_remainder + col.1 + col.0.iter().map(|(k, &v)| v * mapping.get(k).unwrap().unwrap() as i32).sum::<i32>();
+    if column_value.rem_euclid(base) == 0 { Some(column_value / base) } else { None }
+}
+
+fn char_value(ch: char, mapping: &Mapping) -> Option<u8> {
+    literal_digit(ch).or_else(|| *mapping.get(&ch).unwrap())
+}
+
+fn signed_value(negative: bool, value: i128) -> i128 {
+    if negative { -value } else { value }
+}
+
+fn apply_mapping_modulo(column_idx: usize, equation: &EquationBatch, base: u8, mapping: &Mapping) -> bool {
+    // Lowest digits of sums, differences and products depend only on the same count of lowest digits of the words
+    let modulus = match (base as i128).checked_pow(column_idx as u32 + 1) {
+        Some(modulus) if modulus <= i64::MAX as i128 => modulus,
+        // Product of such remainders could overflow, leave it for the exact check of the last column
+        _ => return true,
+    };
+    let truncated_value = |word: &String| word.chars()
+        .take(column_idx + 1)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .fold(0i128, |acc, ch| acc * base as i128 + char_value(ch, mapping).unwrap() as i128);
+
+    equation.terms.iter()
+        .map(|term| signed_value(term.negative, term.factors.iter().fold(1i128, |acc, word| acc * truncated_value(word) % modulus)))
+        .fold(0i128, |acc, value| (acc + value).rem_euclid(modulus)) == 0
+}
+
+fn apply_mapping_to_equation(equation: &EquationBatch, base: u8, mapping: &Mapping) -> bool {
+    let word_value = |word: &String| word.chars()
+        .rev()
+        .try_fold(0i128, |acc, ch| acc.checked_mul(base as i128)?.checked_add(char_value(ch, mapping).unwrap() as i128));
+
+    equation.terms.iter()
+        .map(|term| term.factors.iter().try_fold(1i128, |acc, word| acc.checked_mul(word_value(word)?)).map(|value| signed_value(term.negative, value)))
+        .try_fold(0i128, |acc, value| acc.checked_add(value?)) == Some(0)
+}
+
+fn check_column(column_idx: usize, batch: &InputBatch, last_remainders: &[i32], mapping: &Mapping) -> Option<Vec<i32>> {
+    batch.equations.iter()
+        .zip(last_remainders)
+        .map(|(equation, &last_remainder)| if equation.is_linear {
+            apply_mapping_to_column(last_remainder, &equation.columns[column_idx], batch.base, mapping)
+        } else {
+            apply_mapping_modulo(column_idx, equation, batch.base, mapping).then_some(0)
+        })
+        .collect()
+}
+
+fn check_last_column(batch: &InputBatch, last_remainders: &[i32], mapping: &Mapping) -> bool {
+    // Nothing may be carried out of the last column, products need the exact check
+    batch.equations.iter()
+        .zip(last_remainders)
+        .all(|(equation, &last_remainder)| if equation.is_linear {
+            last_remainder == 0
+        } else {
+            apply_mapping_to_equation(equation, batch.base, mapping)
+        })
+}
+
+fn check_no_leftside_zeros(batch: &InputBatch, mapping: &Mapping) -> bool {
+    for line in batch.lines.iter() {
+        if let Some(left_side_char) = line.chars().last() {
+            if let Some(left_side_value) = char_value(left_side_char, mapping) {
+                if left_side_value == 0 {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+fn build_result(column_idx: usize, batch: &InputBatch, last_remainders: &[i32], mapping: &Mapping) -> Option<Mapping> {
+    let column_solution = check_column(column_idx, batch, last_remainders, mapping);
+
+    if let Some(last_remainders) = column_solution {
+        // Seems valid
+        return if column_idx == batch.columns_count - 1 {
+            check_last_column(batch, &last_remainders, mapping).then(|| mapping.clone())
+        } else {
+            find_column_mapping(column_idx + 1, batch, &last_remainders, mapping.clone())
+        }
+    }
+    None
+}
+
+fn find_chars_mapping(not_mapped_chars: &[char], column_idx: usize, batch: &InputBatch, last_remainders: &[i32], mapping: &mut Mapping) -> Option<Mapping> {
+    // Every char of the column mapped - validate and go to the next column
+    let Some((&ch, rest_chars)) = not_mapped_chars.split_first() else {
+        if !check_no_leftside_zeros(batch, mapping) {
+            return None;
+        }
+        return build_result(column_idx, batch, last_remainders, mapping);
+    };
+
+    // Draw only values allowed for the char, dropping already mapped
+    for &value in batch.domains[&ch].iter() {
+        if mapping.values().contains(&Some(value)) {
+            continue;
+        }
+        *mapping.get_mut(&ch).unwrap() = Some(value);
+
+        if let Some(result) = find_chars_mapping(rest_chars, column_idx, batch, last_remainders, mapping) {
+            return Some(result);
+        }
+    }
+    *mapping.get_mut(&ch).unwrap() = None;
+
+    None
+}
+
+fn find_column_mapping(column_idx: usize, batch: &InputBatch, last_remainders: &[i32], mut mapping: Mapping) -> Option<Mapping> {
+    let required_chars = &batch.columns_chars[column_idx];
+
+    let not_mapped_chars: Vec<char> = required_chars.iter().copied().filter(|key| mapping.get(key).unwrap().is_none()).collect();
+
+    find_chars_mapping(&not_mapped_chars, column_idx, batch, last_remainders, &mut mapping)
+}
+
+/// Backtracking as it was before letters got dense indices, cloning maps of digits per column.
+/// Kept only as the baseline `solver_bt` is benchmarked against.
+pub fn solve(input: &str) -> Option<HashMap<char, u8>> {
+    solve_puzzle(&Puzzle::parse(input).ok()?)
+}
+
+pub fn solve_puzzle(puzzle: &Puzzle) -> Option<HashMap<char, u8>> {
+    solve_with(puzzle, &SolveOptions::default())
+}
+
+pub fn solve_with(puzzle: &Puzzle, options: &SolveOptions) -> Option<HashMap<char, u8>> {
+    let batch = InputBatch::from_puzzle(puzzle, options);
+
+    // Letters with a single allowed digit are bound upfront
+    let mapping: Mapping = batch.unique_chars.iter()
+        .map(|ch| (*ch, Some(&batch.domains[ch][..]).filter(|domain| domain.len() == 1).map(|domain| domain[0])))
+        .collect();
+    if !mapping.values().flatten().all_unique() {
+        return None;
+    }
+
+    find_column_mapping(0, &batch, &vec![0; batch.equations.len()], mapping)
+        .map(|mapping| mapping.into_iter().map(|(ch, digit)| (ch, digit.unwrap())).collect())
+}
+
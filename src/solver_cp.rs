@@ -0,0 +1,303 @@
+#![allow(unused)]
+use std::{cmp::Reverse, collections::HashMap, time::Instant};
+use itertools::Itertools;
+
+use crate::options::{LeadingZeros, LetterOrder, SolveOptions};
+use crate::puzzle::{literal_digit, ParseOptions, Puzzle};
+use crate::solution::Solution;
+use crate::solver_bt::{self, InputBatch, Mapping, MAX_LETTERS, UNMAPPED};
+use crate::stats::SolveStats;
+
+/// Letter or carry taking part in a column constraint.
+#[derive(Debug, Clone, Copy)]
+enum Variable {
+    Letter(usize),
+    Carry(usize),
+}
+
+/// Column of a linear equation as `sum(coefficient * variable) + constant == 0`,
+/// with carry into the column counted once and carry out of it `base` times negative.
+#[derive(Debug)]
+struct Constraint {
+    terms: Vec<(Variable, i64)>,
+    constant: i64,
+}
+
+/// Values left for every variable - digits of letters as bitmasks, carries as ranges.
+#[derive(Debug, Clone)]
+struct State {
+    letters: Vec<u64>,
+    carries: Vec<(i64, i64)>,
+}
+
+#[derive(Debug)]
+struct Model {
+    batch: InputBatch,
+    constraints: Vec<Constraint>,
+    degrees: Vec<usize>,
+}
+
+pub(crate) fn floor_div(a: i64, b: i64) -> i64 {
+    let d = a / b;
+    if a % b != 0 && ((a < 0) != (b < 0)) { d - 1 } else { d }
+}
+
+pub(crate) fn ceil_div(a: i64, b: i64) -> i64 {
+    -floor_div(-a, b)
+}
+
+fn range_mask(lo: i64, hi: i64) -> u64 {
+    if hi < 0 || lo > 63 || lo > hi {
+        return 0;
+    }
+    let (lo, hi) = (lo.max(0) as u32, hi.min(63) as u32);
+    (((1u128 << (hi + 1)) - 1) ^ ((1u128 << lo) - 1)) as u64
+}
+
+impl Model {
+    fn from_batch(batch: InputBatch) -> (Self, State) {
+        let base = batch.base as i64;
+
+        let mut constraints = vec![];
+        let mut carries = vec![];
+        for equation in batch.equations.iter().filter(|equation| equation.is_linear) {
+            // Carries are bounded by the biggest possible column value, propagation narrows them down
+            let carry_bound: i64 = equation.columns.iter()
+                .map(|(letters, constant)| letters.iter().map(|&(_, v)| v.abs() as i64).sum::<i64>() * (base - 1) + constant.abs() as i64)
+                .sum();
+
+            let mut carry_in = None;
+            for (column_idx, (letters, constant)) in equation.columns.iter().enumerate() {
+                let mut terms: Vec<_> = letters.iter()
+                    .filter(|&&(_, coefficient)| coefficient != 0)
+                    .map(|&(letter, coefficient)| (Variable::Letter(letter), coefficient as i64))
+                    .collect();
+                if let Some(carry) = carry_in {
+                    terms.push((Variable::Carry(carry), 1));
+                }
+                // Nothing may be carried out of the last column
+                carry_in = if column_idx < batch.columns_count - 1 {
+                    carries.push((-carry_bound, carry_bound));
+                    terms.push((Variable::Carry(carries.len() - 1), -base));
+                    Some(carries.len() - 1)
+                } else {
+                    None
+                };
+                constraints.push(Constraint { terms, constant: *constant as i64 });
+            }
+        }
+
+        let letters = batch.domains.clone();
+
+        let mut degrees = vec![0; letters.len()];
+        for constraint in constraints.iter() {
+            for (variable, _) in constraint.terms.iter() {
+                if let Variable::Letter(idx) = variable {
+                    degrees[*idx] += 1;
+                }
+            }
+        }
+
+        (Self { batch, constraints, degrees }, State { letters, carries })
+    }
+
+    fn bounds(variable: Variable, state: &State) -> (i64, i64) {
+        match variable {
+            Variable::Letter(idx) => {
+                let digits = state.letters[idx];
+                (digits.trailing_zeros() as i64, 63 - digits.leading_zeros() as i64)
+            }
+            Variable::Carry(idx) => state.carries[idx],
+        }
+    }
+
+    /// Bounds consistency - narrows every variable to values for which the rest of the column can still sum up to zero.
+    fn propagate_constraint(constraint: &Constraint, state: &mut State) -> Option<bool> {
+        let term_bounds = |variable, coefficient: i64, state: &State| {
+            let (lo, hi) = Self::bounds(variable, state);
+            if coefficient > 0 { (coefficient * lo, coefficient * hi) } else { (coefficient * hi, coefficient * lo) }
+        };
+
+        let (mut total_min, mut total_max) = (constraint.constant, constraint.constant);
+        for &(variable, coefficient) in constraint.terms.iter() {
+            let (term_min, term_max) = term_bounds(variable, coefficient, state);
+            total_min += term_min;
+            total_max += term_max;
+        }
+        if total_min > 0 || total_max < 0 {
+            return None;
+        }
+
+        let mut changed = false;
+        for &(variable, coefficient) in constraint.terms.iter() {
+            let (term_min, term_max) = term_bounds(variable, coefficient, state);
+            let (rest_min, rest_max) = (total_min - term_min, total_max - term_max);
+            let (lo, hi) = if coefficient > 0 {
+                (ceil_div(-rest_max, coefficient), floor_div(-rest_min, coefficient))
+            } else {
+                (ceil_div(-rest_min, coefficient), floor_div(-rest_max, coefficient))
+            };
+
+            match variable {
+                Variable::Letter(idx) => {
+                    let digits = state.letters[idx] & range_mask(lo, hi);
+                    if digits == 0 {
+                        return None;
+                    }
+                    changed |= digits != state.letters[idx];
+                    state.letters[idx] = digits;
+                }
+                Variable::Carry(idx) => {
+                    let (old_lo, old_hi) = state.carries[idx];
+                    let (lo, hi) = (old_lo.max(lo), old_hi.min(hi));
+                    if lo > hi {
+                        return None;
+                    }
+                    changed |= (lo, hi) != (old_lo, old_hi);
+                    state.carries[idx] = (lo, hi);
+                }
+            }
+        }
+        Some(changed)
+    }
+
+    /// All different - digit of a mapped letter is removed from the others and there must be enough digits left for all letters.
+    fn propagate_all_different(state: &mut State) -> Option<bool> {
+        let mut changed = false;
+        for idx in 0..state.letters.len() {
+            let digit = state.letters[idx];
+            if digit.count_ones() != 1 {
+                continue;
+            }
+            for other_idx in (0..state.letters.len()).filter(|&other_idx| other_idx != idx) {
+                if state.letters[other_idx] & digit != 0 {
+                    state.letters[other_idx] &= !digit;
+                    if state.letters[other_idx] == 0 {
+                        return None;
+                    }
+                    changed = true;
+                }
+            }
+        }
+
+        let all_digits = state.letters.iter().fold(0u64, |acc, digits| acc | digits);
+        if state.letters.contains(&0) || (all_digits.count_ones() as usize) < state.letters.len() {
+            return None;
+        }
+        Some(changed)
+    }
+
+    fn propagate(&self, state: &mut State) -> bool {
+        loop {
+            let mut changed = false;
+            for constraint in self.constraints.iter() {
+                match Self::propagate_constraint(constraint, state) {
+                    Some(constraint_changed) => changed |= constraint_changed,
+                    None => return false,
+                }
+            }
+            match Self::propagate_all_different(state) {
+                Some(letters_changed) => changed |= letters_changed,
+                None => return false,
+            }
+            if !changed {
+                return true;
+            }
+        }
+    }
+
+    fn mapping(&self, state: &State) -> Mapping {
+        let mut mapping = [UNMAPPED; MAX_LETTERS];
+        for (digit, digits) in mapping.iter_mut().zip(state.letters.iter()) {
+            *digit = digits.trailing_zeros() as u8;
+        }
+        mapping
+    }
+
+    fn search(&self, mut state: State, depth: usize, stats: &mut SolveStats) -> Option<State> {
+        stats.max_depth = stats.max_depth.max(depth);
+        if !self.propagate(&mut state) {
+            stats.pruned_column_mismatch += 1;
+            return None;
+        }
+
+        // Most constrained letter first - fewest digits left, then most columns it takes part in
+        let letter = (0..state.letters.len())
+            .filter(|&idx| state.letters[idx].count_ones() > 1)
+            .min_by_key(|&idx| (state.letters[idx].count_ones(), Reverse(self.degrees[idx])));
+
+        let Some(letter) = letter else {
+            // Products are not propagated, so every complete mapping is checked as in backtracking
+            stats.permutations += 1;
+            let valid = solver_bt::check_mapping(&self.batch, &self.mapping(&state));
+            if !valid {
+                stats.pruned_column_mismatch += 1;
+            }
+            return valid.then_some(state);
+        };
+
+        let mut digits = state.letters[letter];
+        while digits != 0 {
+            let digit = digits & digits.wrapping_neg();
+            digits &= !digit;
+
+            let mut next_state = state.clone();
+            next_state.letters[letter] = digit;
+            stats.nodes += 1;
+            if let Some(solution) = self.search(next_state, depth + 1, stats) {
+                return Some(solution);
+            }
+        }
+        None
+    }
+}
+
+pub fn solve(input: &str) -> Option<Solution> {
+    solve_puzzle(&Puzzle::parse(input).ok()?)
+}
+
+pub fn solve_puzzle(puzzle: &Puzzle) -> Option<Solution> {
+    solve_with(puzzle, &SolveOptions::default())
+}
+
+pub fn solve_with(puzzle: &Puzzle, options: &SolveOptions) -> Option<Solution> {
+    solve_with_stats(puzzle, options).0
+}
+
+pub fn solve_with_stats(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, SolveStats) {
+    let start = Instant::now();
+    let mut stats = SolveStats::default();
+    let (model, state) = Model::from_batch(InputBatch::from_puzzle(puzzle, options));
+    let solution = model.search(state, 0, &mut stats).map(|solution| model.batch.result(&model.mapping(&solution)));
+    stats.elapsed = start.elapsed();
+    (solution, stats)
+}
+
+#[test]
+fn propagation_prunes_search() {
+    // Puzzles propagation alone solves or refutes take no branching at all
+    for input in ["I + BB == ILL", "A + A + A + A + A + A + A + A + A + A + A + B == BCC", "ACA + DD == BD", "AB + A == C"] {
+        let (answer, stats) = solve_with_stats(&Puzzle::parse(input).unwrap(), &SolveOptions::default());
+        assert_eq!(answer, solver_bt::solve(input), "{}", input);
+        assert_eq!((stats.nodes, stats.max_depth), (0, 0), "{}", input);
+    }
+
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let (answer, stats) = solve_with_stats(&puzzle, &SolveOptions::default());
+    assert!(answer.is_some());
+    assert!(stats.nodes * 100 < solver_bt::solve_with_stats(&puzzle, &SolveOptions::default()).1.nodes);
+    assert_eq!(stats.permutations, 1);
+}
+
+#[test]
+fn first_solution_follows_letter_order() {
+    // Letters with as many digits left and as many constraints are tried in the letter order
+    let puzzle = Puzzle::parse("C + B == A").unwrap();
+    let expected = [('C', 1), ('B', 2), ('A', 3)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+
+    let options = SolveOptions::new().letter_order(LetterOrder::Alphabetical);
+    let expected = [('A', 3), ('B', 1), ('C', 2)].into_iter().collect();
+    assert_eq!(solve_with(&puzzle, &options), Some(expected));
+}
+
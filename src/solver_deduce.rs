@@ -0,0 +1,642 @@
+#![allow(unused)]
+use std::{collections::HashMap, fmt, time::Instant};
+use itertools::Itertools;
+
+use crate::options::{LeadingZeros, SolveOptions};
+use crate::puzzle::{ParseOptions, Puzzle};
+use crate::solution::Solution;
+use crate::solver_bt::{self, InputBatch, Mapping, Symbol, MAX_LETTERS, UNMAPPED};
+use crate::solver_cp::{ceil_div, floor_div};
+use crate::stats::SolveStats;
+
+/// Columns with more digit combinations than this are left to the carry bounds and to guessing.
+const MAX_CASES: u64 = 4096;
+
+/// Deduction rule a step of a proof is based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    /// Words do not start with zero.
+    LeadingZero,
+    /// Digit of a known letter is not available to the others, and there must be a digit for every letter.
+    AllDifferent,
+    /// What a column adds up to bounds the carry out of it and the carry into it.
+    CarryBounds,
+    /// Result longer than the addends starts with the carry out of the column before, which is 1 for two addends.
+    LeadingCarry,
+    /// Letter on both sides of a column cancels out, so the carries or the rest of the column are bounded without it.
+    IdenticalLetters,
+    /// When everything but one letter in a column is known or a multiple of a divisor of the base, like in `X + X`,
+    /// the letter must leave the right remainder.
+    Parity,
+    /// Column holds for only some of the digits left.
+    ColumnCases,
+    /// No rule applies, so a digit is supposed and the other ones are tried if it leads to a contradiction.
+    Guess,
+}
+
+/// Single sentence of a proof, steps of a supposition are nested one level deeper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub rule: Rule,
+    pub depth: usize,
+    pub text: String,
+}
+
+/// Reasoning of `solver_deduce`, rendered as text one step per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Proof {
+    pub steps: Vec<Step>,
+    /// Why the puzzle has no solution.
+    pub contradiction: Option<String>,
+}
+
+impl Proof {
+    /// Rules which fired, in order of the first time they did.
+    pub fn rules(&self) -> Vec<Rule> {
+        self.steps.iter().map(|step| step.rule).unique().collect()
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rule::LeadingZero => "leading zero",
+            Rule::AllDifferent => "all different",
+            Rule::CarryBounds => "carry bounds",
+            Rule::LeadingCarry => "leading carry",
+            Rule::IdenticalLetters => "identical letters",
+            Rule::Parity => "parity",
+            Rule::ColumnCases => "column cases",
+            Rule::Guess => "guess",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in self.steps.iter() {
+            writeln!(f, "{}{}", "  ".repeat(step.depth), step.text)?;
+        }
+        if let Some(contradiction) = &self.contradiction {
+            writeln!(f, "No solution: {}.", contradiction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Digits left for every letter as bitmasks and carries as ranges, indexed like in `solver_bt`.
+#[derive(Debug, Clone)]
+struct State {
+    letters: Vec<u64>,
+    carries: Vec<(i64, i64)>,
+}
+
+/// Digits of the column letters with carry in and carry out, for every way the column holds.
+#[derive(Debug)]
+struct Support {
+    letters: Vec<usize>,
+    cases: Vec<(Vec<u8>, i64, i64)>,
+}
+
+/// Rule which fired with its explanation, or a contradiction.
+type Deduction = Result<Option<(Rule, String)>, String>;
+
+fn digits(mask: u64) -> impl Iterator<Item = u8> {
+    (0..64u8).filter(move |&digit| mask & 1 << digit != 0)
+}
+
+fn describe_letter(ch: char, mask: u64) -> String {
+    match mask.count_ones() {
+        1 => format!("{} = {}", ch, mask.trailing_zeros()),
+        _ => format!("{} is one of {}", ch, digits(mask).join(", ")),
+    }
+}
+
+fn describe_range(name: &str, (lo, hi): (i64, i64)) -> String {
+    if lo == hi { format!("{} = {}", name, lo) } else { format!("{} is between {} and {}", name, lo, hi) }
+}
+
+fn describe_bounds((lo, hi): (i64, i64)) -> String {
+    if lo == hi { lo.to_string() } else { format!("between {} and {}", lo, hi) }
+}
+
+fn term(ch: impl fmt::Display, coefficient: i64) -> String {
+    match coefficient.abs() {
+        1 => ch.to_string(),
+        times => format!("{}*{}", times, ch),
+    }
+}
+
+#[derive(Debug)]
+struct Model {
+    batch: InputBatch,
+}
+
+impl Model {
+    fn carry_in(&self, column_idx: usize, equation_idx: usize) -> usize {
+        column_idx * self.batch.equations.len() + equation_idx
+    }
+
+    fn carry_out(&self, column_idx: usize, equation_idx: usize) -> usize {
+        self.carry_in(column_idx + 1, equation_idx)
+    }
+
+    /// Columns of sums and differences, products are only checked once every letter is known.
+    fn linear_columns(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.batch.columns_count).cartesian_product(0..self.batch.equations.len())
+            .filter(|&(_, equation_idx)| self.batch.equations[equation_idx].is_linear)
+    }
+
+    fn letters(&self, column_idx: usize, equation_idx: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        self.batch.equations[equation_idx].columns[column_idx].0.iter()
+            .filter(|&&(_, coefficient)| coefficient != 0)
+            .map(|&(letter, coefficient)| (letter, coefficient as i64))
+    }
+
+    /// Letters counted as often on the left side of a column as on the right one.
+    fn cancelled(&self, column_idx: usize, equation_idx: usize) -> Vec<char> {
+        self.batch.equations[equation_idx].columns[column_idx].0.iter()
+            .filter(|&&(_, coefficient)| coefficient == 0)
+            .map(|&(letter, _)| self.batch.unique_chars[letter])
+            .collect()
+    }
+
+    /// Rule of a step about a column, letters which cancel out are what lets the step be made.
+    fn column_rule(&self, column_idx: usize, equation_idx: usize, rule: Rule) -> Rule {
+        if self.cancelled(column_idx, equation_idx).is_empty() { rule } else { Rule::IdenticalLetters }
+    }
+
+    /// Column written out with letters, e.g. `column 3 (N + R + c2 = E + 10*c3)`.
+    fn column_name(&self, column_idx: usize, equation_idx: usize) -> String {
+        let name = match self.batch.equations.len() {
+            1 => format!("column {}", column_idx + 1),
+            _ => format!("column {} of equation {}", column_idx + 1, equation_idx + 1),
+        };
+        match self.cancelled(column_idx, equation_idx).as_slice() {
+            [] => format!("{} ({})", name, self.formula(column_idx, equation_idx)),
+            cancelled => format!("{} ({}, {} cancels out)", name, self.formula(column_idx, equation_idx), cancelled.iter().join(", ")),
+        }
+    }
+
+    fn formula(&self, column_idx: usize, equation_idx: usize) -> String {
+        let (letters, constant) = &self.batch.equations[equation_idx].columns[column_idx];
+        let constant = *constant as i64;
+        let mut terms: Vec<(i64, String)> = letters.iter()
+            .flat_map(|&(letter, coefficient)| {
+                let (ch, coefficient) = (self.batch.unique_chars[letter], coefficient as i64);
+                match coefficient {
+                    0 => vec![(1, ch.to_string()), (-1, ch.to_string())],
+                    _ => vec![(coefficient, term(ch, coefficient))],
+                }
+            })
+            .collect();
+        if constant != 0 {
+            terms.push((constant, constant.abs().to_string()));
+        }
+        if column_idx > 0 {
+            terms.push((1, format!("c{}", column_idx)));
+        }
+        terms.push((-1, format!("{}*c{}", self.batch.base, column_idx + 1)));
+
+        let side = |positive: bool| {
+            let side_terms: Vec<&str> = terms.iter()
+                .filter(|(sign, _)| (*sign > 0) == positive)
+                .map(|(_, text)| text.as_str())
+                .collect();
+            if side_terms.is_empty() { "0".to_string() } else { side_terms.join(" + ") }
+        };
+        format!("{} = {}", side(true), side(false))
+    }
+
+    fn initial_state(&self) -> State {
+        let base = self.batch.base as i64;
+        let equations_count = self.batch.equations.len();
+        let mut carries = vec![(0, 0); (self.batch.columns_count + 1) * equations_count];
+        for (equation_idx, equation) in self.batch.equations.iter().enumerate().filter(|(_, equation)| equation.is_linear) {
+            // Carries are bounded by the biggest possible column value, the rules narrow them down
+            let carry_bound: i64 = equation.columns.iter()
+                .map(|(letters, constant)| letters.iter().map(|&(_, v)| v.abs() as i64).sum::<i64>() * (base - 1) + constant.abs() as i64)
+                .sum();
+            for column_idx in 1..self.batch.columns_count {
+                carries[self.carry_in(column_idx, equation_idx)] = (-carry_bound, carry_bound);
+            }
+        }
+        State { letters: self.batch.domains.clone(), carries }
+    }
+
+    fn all_different(&self, state: &mut State) -> Deduction {
+        for letter in (0..state.letters.len()).filter(|&letter| state.letters[letter].count_ones() == 1) {
+            let digit = state.letters[letter];
+            let others: Vec<usize> = (0..state.letters.len())
+                .filter(|&other| other != letter && state.letters[other] & digit != 0)
+                .collect();
+            if others.is_empty() {
+                continue;
+            }
+            for other in others {
+                state.letters[other] &= !digit;
+            }
+            let (ch, digit) = (self.batch.unique_chars[letter], digit.trailing_zeros());
+            return Ok(Some((Rule::AllDifferent, format!("{} = {}, so no other letter can be {}.", ch, digit, digit))));
+        }
+
+        let all_digits = state.letters.iter().fold(0u64, |acc, digits| acc | digits);
+        if (all_digits.count_ones() as usize) < state.letters.len() {
+            return Err(format!("only {} digits are left for {} letters", all_digits.count_ones(), state.letters.len()));
+        }
+        Ok(None)
+    }
+
+    fn carry_bounds(&self, state: &mut State) -> Deduction {
+        let base = self.batch.base as i64;
+        for (column_idx, equation_idx) in self.linear_columns() {
+            let (carry_in_idx, carry_out_idx) = (self.carry_in(column_idx, equation_idx), self.carry_out(column_idx, equation_idx));
+            let ((in_lo, in_hi), (out_lo, out_hi)) = (state.carries[carry_in_idx], state.carries[carry_out_idx]);
+            let constant = self.batch.equations[equation_idx].columns[column_idx].1 as i64;
+            let (lo, hi) = self.letters(column_idx, equation_idx)
+                .fold((constant, constant), |(lo, hi), (letter, coefficient)| {
+                    let digits = state.letters[letter];
+                    let (min, max) = (coefficient * digits.trailing_zeros() as i64, coefficient * (63 - digits.leading_zeros()) as i64);
+                    (lo + min.min(max), hi + min.max(max))
+                });
+            let column = self.column_name(column_idx, equation_idx);
+            let rule = self.column_rule(column_idx, equation_idx, Rule::CarryBounds);
+
+            let carry_out = (out_lo.max(ceil_div(lo + in_lo, base)), out_hi.min(floor_div(hi + in_hi, base)));
+            let carry_in = (in_lo.max(base * out_lo - hi), in_hi.min(base * out_hi - lo));
+            if carry_out.0 > carry_out.1 || carry_in.0 > carry_in.1 {
+                return Err(format!("{} cannot add up with any carry", column));
+            }
+            if carry_out != (out_lo, out_hi) {
+                state.carries[carry_out_idx] = carry_out;
+                let carry = describe_range(&format!("c{}", column_idx + 1), carry_out);
+                return Ok(Some((rule, format!("in {} {}*c{} must be {}, so {}.", column, base, column_idx + 1, describe_bounds((lo + in_lo, hi + in_hi)), carry))));
+            }
+            if carry_in != (in_lo, in_hi) {
+                state.carries[carry_in_idx] = carry_in;
+                let bounds = (base * out_lo - hi, base * out_hi - lo);
+                let text = if bounds == carry_in {
+                    format!("in {} c{} must be {}.", column, column_idx, describe_bounds(bounds))
+                } else {
+                    format!("in {} c{} must be {}, so {}.", column, column_idx, describe_bounds(bounds), describe_range(&format!("c{}", column_idx), carry_in))
+                };
+                return Ok(Some((rule, text)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn leading_carry(&self, state: &mut State) -> Deduction {
+        for (column_idx, equation_idx) in self.linear_columns().filter(|&(column_idx, _)| column_idx > 0) {
+            // Column of a single result letter and the carry into it, with nothing carried out
+            let letters: Vec<(usize, i64)> = self.letters(column_idx, equation_idx).collect();
+            let constant = self.batch.equations[equation_idx].columns[column_idx].1;
+            let &[(letter, -1)] = letters.as_slice() else { continue };
+            if constant != 0 || state.carries[self.carry_out(column_idx, equation_idx)] != (0, 0) {
+                continue;
+            }
+
+            let carry_in = state.carries[self.carry_in(column_idx, equation_idx)];
+            let digits = state.letters[letter] & digits(u64::MAX).filter(|&digit| (carry_in.0..=carry_in.1).contains(&(digit as i64))).fold(0, |acc, digit| acc | 1 << digit);
+            if digits == state.letters[letter] {
+                continue;
+            }
+            state.letters[letter] = digits;
+            let ch = self.batch.unique_chars[letter];
+            let column = self.column_name(column_idx, equation_idx);
+            let carry = describe_range(&format!("c{}", column_idx), carry_in);
+            if digits == 0 {
+                return Err(format!("{} is the carry out of column {} and {}, which leaves no digit for {}", ch, column_idx, carry, ch));
+            }
+            let text = format!("{} makes {} the carry out of column {}, and {}, so {}.", column, ch, column_idx, carry, describe_letter(ch, digits));
+            return Ok(Some((Rule::LeadingCarry, text)));
+        }
+        Ok(None)
+    }
+
+    fn parity(&self, state: &mut State) -> Deduction {
+        let base = self.batch.base as i64;
+        for (column_idx, equation_idx) in self.linear_columns() {
+            let (carry_lo, carry_hi) = state.carries[self.carry_in(column_idx, equation_idx)];
+            if carry_lo != carry_hi {
+                continue;
+            }
+            let (unknown, known): (Vec<_>, Vec<_>) = self.letters(column_idx, equation_idx)
+                .partition(|&(letter, _)| state.letters[letter].count_ones() > 1);
+            if unknown.len() < 2 {
+                continue;
+            }
+            let known_sum = carry_lo + self.batch.equations[equation_idx].columns[column_idx].1 as i64 + known.iter()
+                .map(|&(letter, coefficient)| coefficient * state.letters[letter].trailing_zeros() as i64)
+                .sum::<i64>();
+
+            for divisor in (2..base).filter(|divisor| base % divisor == 0) {
+                // Every unknown letter but one is a multiple of the divisor, so is the carry out
+                let (multiples, rest): (Vec<_>, Vec<_>) = unknown.iter().partition(|&&(_, coefficient)| coefficient % divisor == 0);
+                let &[(letter, coefficient)] = rest.as_slice() else { continue };
+                let allowed = digits(state.letters[letter])
+                    .filter(|&digit| (coefficient * digit as i64 + known_sum) % divisor == 0)
+                    .fold(0u64, |acc, digit| acc | 1 << digit);
+                if allowed == state.letters[letter] {
+                    continue;
+                }
+                state.letters[letter] = allowed;
+                let ch = self.batch.unique_chars[letter];
+                let column = self.column_name(column_idx, equation_idx);
+                if allowed == 0 {
+                    return Err(format!("no digit of {} fits {} modulo {}", ch, column, divisor));
+                }
+                let multiples = multiples.iter()
+                    .map(|&(other, coefficient)| term(self.batch.unique_chars[other], coefficient))
+                    .chain([format!("{}*c{}", base, column_idx + 1)])
+                    .join(", ");
+                let kind = match divisor {
+                    2 if allowed & 0x5555_5555_5555_5555 == allowed => "even".to_string(),
+                    2 => "odd".to_string(),
+                    _ => format!("fit modulo {}", divisor),
+                };
+                let text = format!("in {} {} are multiples of {} and the rest is known, so {} must be {}: {}.", column, multiples, divisor, ch, kind, describe_letter(ch, allowed));
+                return Ok(Some((Rule::Parity, text)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn column_cases(&self, state: &mut State) -> Deduction {
+        for (column_idx, equation_idx) in self.linear_columns() {
+            if let Some(cases) = self.column_cases_of(state, column_idx, equation_idx)? {
+                let text = format!("{} only holds when {}.", self.column_name(column_idx, equation_idx), cases);
+                return Ok(Some((self.column_rule(column_idx, equation_idx, Rule::ColumnCases), text)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Ways the column holds, if there are few enough to list and they narrow anything down.
+    fn support(&self, state: &State, column_idx: usize, equation_idx: usize) -> Option<Support> {
+        let base = self.batch.base as i64;
+        let letters: Vec<(usize, i64)> = self.letters(column_idx, equation_idx).collect();
+        let (carry_in, carry_out) = (state.carries[self.carry_in(column_idx, equation_idx)], state.carries[self.carry_out(column_idx, equation_idx)]);
+        // Many letters in a column of a big base overflow the count, which is then too big anyway
+        let size = letters.iter()
+            .map(|&(letter, _)| state.letters[letter].count_ones() as u64)
+            .chain([(carry_in.1 - carry_in.0 + 1) as u64])
+            .try_fold(1u64, |size, count| size.checked_mul(count));
+        if size.is_none_or(|size| size > MAX_CASES) {
+            return None;
+        }
+
+        let constant = self.batch.equations[equation_idx].columns[column_idx].1 as i64;
+        let combinations: Vec<Vec<u8>> = if letters.is_empty() {
+            vec![vec![]]
+        } else {
+            letters.iter()
+                .map(|&(letter, _)| digits(state.letters[letter]).collect::<Vec<_>>())
+                .multi_cartesian_product()
+                .filter(|combination| combination.iter().all_unique())
+                .collect()
+        };
+        let cases = combinations.into_iter()
+            .cartesian_product(carry_in.0..=carry_in.1)
+            .filter_map(|(combination, carry)| {
+                let total = constant + carry + letters.iter().zip(combination.iter()).map(|(&(_, coefficient), &digit)| coefficient * digit as i64).sum::<i64>();
+                (total.rem_euclid(base) == 0 && (carry_out.0..=carry_out.1).contains(&(total / base))).then(|| (combination, carry, total / base))
+            })
+            .collect();
+        Some(Support { letters: letters.iter().map(|&(letter, _)| letter).collect(), cases })
+    }
+
+    /// Narrows the column down to the ways it holds, describing them.
+    fn column_cases_of(&self, state: &mut State, column_idx: usize, equation_idx: usize) -> Result<Option<String>, String> {
+        let Some(support) = self.support(state, column_idx, equation_idx) else { return Ok(None) };
+        if support.cases.is_empty() {
+            return Err(format!("{} cannot add up with the digits left", self.column_name(column_idx, equation_idx)));
+        }
+
+        let (carry_in_idx, carry_out_idx) = (self.carry_in(column_idx, equation_idx), self.carry_out(column_idx, equation_idx));
+        let letters: Vec<u64> = (0..support.letters.len())
+            .map(|idx| support.cases.iter().fold(0u64, |acc, (combination, _, _)| acc | 1 << combination[idx]))
+            .collect();
+        let carry_in = support.cases.iter().map(|&(_, carry, _)| carry).minmax().into_option().unwrap();
+        let carry_out = support.cases.iter().map(|&(_, _, carry)| carry).minmax().into_option().unwrap();
+        let narrowed = support.letters.iter().zip(letters.iter()).any(|(&letter, &digits)| digits != state.letters[letter])
+            || carry_in != state.carries[carry_in_idx]
+            || carry_out != state.carries[carry_out_idx];
+        if !narrowed {
+            return Ok(None);
+        }
+
+        // Only what was not known before is described
+        let unknown: Vec<usize> = (0..support.letters.len()).filter(|&idx| state.letters[support.letters[idx]].count_ones() > 1).collect();
+        let (in_known, out_known) = (state.carries[carry_in_idx].0 == state.carries[carry_in_idx].1, state.carries[carry_out_idx].0 == state.carries[carry_out_idx].1);
+        let description = if support.cases.len() <= 3 {
+            support.cases.iter()
+                .map(|(combination, carry_in, carry_out)| unknown.iter()
+                    .map(|&idx| format!("{} = {}", self.batch.unique_chars[support.letters[idx]], combination[idx]))
+                    .chain((!in_known).then(|| format!("c{} = {}", column_idx, carry_in)))
+                    .chain((!out_known).then(|| format!("c{} = {}", column_idx + 1, carry_out)))
+                    .join(", "))
+                .join(" or ")
+        } else {
+            unknown.iter()
+                .filter(|&&idx| letters[idx] != state.letters[support.letters[idx]])
+                .map(|&idx| describe_letter(self.batch.unique_chars[support.letters[idx]], letters[idx]))
+                .chain((carry_in != state.carries[carry_in_idx]).then(|| describe_range(&format!("c{}", column_idx), carry_in)))
+                .chain((carry_out != state.carries[carry_out_idx]).then(|| describe_range(&format!("c{}", column_idx + 1), carry_out)))
+                .join(" and ")
+        };
+
+        for (&letter, &digits) in support.letters.iter().zip(letters.iter()) {
+            state.letters[letter] = digits;
+        }
+        state.carries[carry_in_idx] = carry_in;
+        state.carries[carry_out_idx] = carry_out;
+        Ok(Some(description))
+    }
+
+    /// Applies the first rule which narrows anything down until none does.
+    fn deduce(&self, state: &mut State, depth: usize, steps: &mut Vec<Step>) -> Result<(), String> {
+        let rules: [fn(&Self, &mut State) -> Deduction; 5] = [
+            Self::all_different,
+            Self::carry_bounds,
+            Self::leading_carry,
+            Self::parity,
+            Self::column_cases,
+        ];
+        loop {
+            if let Some(letter) = state.letters.iter().position(|&digits| digits == 0) {
+                return Err(format!("no digit is left for {}", self.batch.unique_chars[letter]));
+            }
+            match rules.iter().find_map(|rule| rule(self, state).transpose()).transpose()? {
+                Some((rule, text)) => {
+                    // Rules start the sentence in lowercase when it opens with a column
+                    let mut chars = text.chars();
+                    let text = chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect());
+                    steps.push(Step { rule, depth, text });
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn search(&self, mut state: State, depth: usize, steps: &mut Vec<Step>, stats: &mut SolveStats) -> Result<Mapping, String> {
+        stats.nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        if let Err(contradiction) = self.deduce(&mut state, depth, steps) {
+            stats.pruned_column_mismatch += 1;
+            return Err(contradiction);
+        }
+
+        // Fewest digits left first
+        let letter = (0..state.letters.len())
+            .filter(|&letter| state.letters[letter].count_ones() > 1)
+            .min_by_key(|&letter| state.letters[letter].count_ones());
+
+        let Some(letter) = letter else {
+            // Products are only checked for the complete mapping
+            stats.permutations += 1;
+            let mut mapping = [UNMAPPED; MAX_LETTERS];
+            for (digit, digits) in mapping.iter_mut().zip(state.letters.iter()) {
+                *digit = digits.trailing_zeros() as u8;
+            }
+            if !solver_bt::check_mapping(&self.batch, &mapping) {
+                stats.pruned_column_mismatch += 1;
+                return Err("the digits do not satisfy the puzzle".to_string());
+            }
+            return Ok(mapping);
+        };
+
+        let ch = self.batch.unique_chars[letter];
+        let candidates: Vec<u8> = digits(state.letters[letter]).collect();
+        for (idx, &digit) in candidates.iter().enumerate() {
+            let mut next_state = state.clone();
+            next_state.letters[letter] = 1 << digit;
+            if idx == candidates.len() - 1 {
+                steps.push(Step { rule: Rule::Guess, depth, text: format!("Only {} = {} is left.", ch, digit) });
+                return self.search(next_state, depth, steps, stats);
+            }
+
+            // Steps of a supposition are kept only if it leads to the solution
+            let mut branch = vec![Step { rule: Rule::Guess, depth, text: format!("Suppose {} = {}.", ch, digit) }];
+            match self.search(next_state, depth + 1, &mut branch, stats) {
+                Ok(mapping) => {
+                    steps.append(&mut branch);
+                    return Ok(mapping);
+                }
+                Err(contradiction) => steps.push(Step { rule: Rule::Guess, depth, text: format!("Suppose {} = {}: {}, so {} cannot be {}.", ch, digit, contradiction, ch, digit) }),
+            }
+        }
+        unreachable!("letter with a single digit is not guessed")
+    }
+}
+
+pub fn solve(input: &str) -> Option<Solution> {
+    solve_puzzle(&Puzzle::parse(input).ok()?)
+}
+
+pub fn solve_puzzle(puzzle: &Puzzle) -> Option<Solution> {
+    solve_with(puzzle, &SolveOptions::default())
+}
+
+pub fn solve_with(puzzle: &Puzzle, options: &SolveOptions) -> Option<Solution> {
+    solve_with_stats(puzzle, options).0
+}
+
+pub fn solve_with_stats(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, SolveStats) {
+    let (solution, _, stats) = deduce(puzzle, options);
+    (solution, stats)
+}
+
+/// Same as `solve_with`, with the deductions which led to the solution or to a contradiction.
+pub fn solve_explained(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, Proof) {
+    let (solution, proof, _) = deduce(puzzle, options);
+    (solution, proof)
+}
+
+fn deduce(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, Proof, SolveStats) {
+    let start = Instant::now();
+    let model = Model { batch: InputBatch::from_puzzle(puzzle, options) };
+    let mut stats = SolveStats::default();
+    let mut proof = Proof::default();
+
+    // Zero is already taken out of the digits of leading letters
+    let leading: Vec<char> = model.batch.leading.iter()
+        .filter_map(|symbol| match symbol {
+            Symbol::Letter(letter) => Some(model.batch.unique_chars[*letter]),
+            Symbol::Digit(_) => None,
+        })
+        .collect();
+    match leading.as_slice() {
+        [] => {}
+        [ch] => proof.steps.push(Step { rule: Rule::LeadingZero, depth: 0, text: format!("{} starts a word, so it cannot be 0.", ch) }),
+        _ => proof.steps.push(Step { rule: Rule::LeadingZero, depth: 0, text: format!("{} start words, so none of them can be 0.", leading.iter().join(", ")) }),
+    }
+
+    let solution = match model.search(model.initial_state(), 0, &mut proof.steps, &mut stats) {
+        Ok(mapping) => Some(model.batch.result(&mapping)),
+        Err(contradiction) => {
+            proof.contradiction = Some(contradiction);
+            None
+        }
+    };
+    stats.elapsed = start.elapsed();
+    (solution, proof, stats)
+}
+
+#[test]
+fn non_ascii_letters_in_proof() {
+    let (answer, proof) = solve_explained(&Puzzle::parse("Ł + ŻŻ == ŁĄĄ").unwrap(), &SolveOptions::default());
+    assert_eq!(answer, Some([('Ł', 1), ('Ż', 9), ('Ą', 0)].into_iter().collect()));
+    assert!(proof.steps.iter().all(|step| step.text.chars().next().is_some_and(|first| !first.is_lowercase())));
+}
+
+#[test]
+fn many_letters_in_column_of_big_base() {
+    // Digits of 16 letters in a base 36 column have more combinations than fit in u64, too many to list
+    let letters: Vec<String> = ('A'..='P').map(|ch| ch.to_string()).collect();
+    let puzzle = Puzzle::parse_with(&format!("{} == QR", letters.join(" + ")), &ParseOptions::new().base(36)).unwrap();
+    let model = Model { batch: InputBatch::from_puzzle(&puzzle, &SolveOptions::default()) };
+    assert!(model.support(&model.initial_state(), 0, 0).is_none());
+}
+
+#[test]
+fn proof_of_send_more_money() {
+    let (answer, proof) = solve_explained(&Puzzle::parse("SEND + MORE == MONEY").unwrap(), &SolveOptions::default());
+    assert_eq!(answer, solve("SEND + MORE == MONEY"));
+    assert_eq!(proof.contradiction, None);
+    assert_eq!(proof.steps[0].text, "S, M start words, so none of them can be 0.");
+    let rules = proof.rules();
+    assert!(rules.contains(&Rule::CarryBounds));
+    assert!(rules.contains(&Rule::LeadingCarry));
+
+    let text = proof.to_string();
+    assert!(text.contains("In column 4 (S + M + c3 = O + 10*c4) 10*c4 must be between -7 and 19, so c4 is between 0 and 1.\n"));
+    assert!(text.contains("Column 5 (c4 = M + 10*c5) makes M the carry out of column 4, and c4 = 1, so M = 1.\n"));
+    assert!(text.contains("Column 4 (S + M + c3 = O + 10*c4) only holds when S = 8, O = 0, c3 = 1 or S = 9, O = 0, c3 = 0.\n"));
+    assert!(text.contains("Suppose E = 5.\n  E = 5, so no other letter can be 5.\n"));
+}
+
+#[test]
+fn proof_rules() {
+    // Same letter in both words of the middle columns
+    let (answer, proof) = solve_explained(&Puzzle::parse("ABC + D == ABE").unwrap(), &SolveOptions::default());
+    assert!(answer.is_some());
+    assert!(proof.rules().contains(&Rule::IdenticalLetters));
+    assert!(proof.to_string().contains("In column 2 (B + c1 = B + 10*c2, B cancels out) 10*c2 must be between 0 and 1, so c2 = 0.\n"));
+
+    // Letter doubled in a column
+    let (answer, proof) = solve_explained(&Puzzle::parse("TO + TO == FOR").unwrap(), &SolveOptions::default());
+    assert_eq!(answer, solver_bt::solve("TO + TO == FOR"));
+    assert!(proof.rules().contains(&Rule::Parity));
+    assert!(proof.to_string().contains("In column 1 (2*O = R + 10*c1) 2*O, 10*c1 are multiples of 2 and the rest is known, so R must be even: R is one of 0, 2, 4, 6, 8.\n"));
+}
+
+#[test]
+fn proof_of_contradiction() {
+    let (answer, proof) = solve_explained(&Puzzle::parse("AB + A == C").unwrap(), &SolveOptions::default());
+    assert_eq!(answer, None);
+    assert!(proof.contradiction.is_some());
+    assert!(proof.to_string().starts_with("A, C start words, so none of them can be 0.\n"));
+    assert!(proof.to_string().ends_with("No solution: column 2 (A + c1 = 10*c2) cannot add up with any carry.\n"));
+}
@@ -0,0 +1,217 @@
+#![allow(unused)]
+use std::{cmp::Reverse, collections::HashMap, time::Instant};
+use itertools::Itertools;
+
+use crate::options::{LeadingZeros, LetterOrder, SolveOptions};
+use crate::puzzle::{ParseOptions, Puzzle};
+use crate::solution::Solution;
+use crate::solver_bt::{self, EquationBatch, InputBatch, Mapping, MAX_LETTERS, UNMAPPED};
+use crate::stats::SolveStats;
+
+/// Linear equation as `sum(coefficient * letter) + constant == 0`.
+#[derive(Debug)]
+struct LinearEquation {
+    coefficients: Vec<i128>,
+    constant: i128,
+}
+
+impl LinearEquation {
+    /// Positional weights of every column summed up, None if they or any sum of the search do not fit in i128.
+    fn from_equation(equation: &EquationBatch, letters_count: usize, base: u8) -> Option<Self> {
+        let mut coefficients = vec![0i128; letters_count];
+        let mut constant = 0i128;
+        let mut weight = 1i128;
+        for (column_idx, (column_letters, column_constant)) in equation.columns.iter().enumerate() {
+            if column_idx > 0 {
+                weight = weight.checked_mul(base as i128)?;
+            }
+            for &(letter, coefficient) in column_letters.iter() {
+                coefficients[letter] = coefficients[letter].checked_add(weight.checked_mul(coefficient as i128)?)?;
+            }
+            constant = constant.checked_add(weight.checked_mul(*column_constant as i128)?)?;
+        }
+
+        // Partial sums and their bounds never get further from zero than the constant and every coefficient times the biggest digit
+        let max_digit = base as i128 - 1;
+        coefficients.iter().try_fold(constant.checked_abs()?, |total, coefficient| total.checked_add(coefficient.checked_abs()?.checked_mul(max_digit)?))?;
+
+        Some(Self { coefficients, constant })
+    }
+}
+
+#[derive(Debug)]
+struct Model {
+    batch: InputBatch,
+    // Letters in search order, coefficients of equations and domains follow it
+    letters: Vec<usize>,
+    domains: Vec<Vec<u8>>,
+    equations: Vec<LinearEquation>,
+    // Range of sum of letters from given depth on, for every equation
+    bounds: Vec<Vec<(i128, i128)>>,
+}
+
+impl Model {
+    fn from_batch(batch: InputBatch) -> Option<Self> {
+        let equations: Vec<_> = batch.equations.iter()
+            .filter(|equation| equation.is_linear)
+            .map(|equation| LinearEquation::from_equation(equation, batch.unique_chars.len(), batch.base))
+            .collect::<Option<_>>()?;
+
+        // Letters with biggest weights first, so partial sums are bounded as early as possible
+        let letters: Vec<usize> = (0..batch.unique_chars.len())
+            .sorted_by_key(|&idx| Reverse(equations.iter().fold(0i128, |sum, equation| sum.saturating_add(equation.coefficients[idx].abs()))))
+            .collect();
+        let equations: Vec<_> = equations.into_iter()
+            .map(|equation| LinearEquation {
+                coefficients: letters.iter().map(|&idx| equation.coefficients[idx]).collect(),
+                constant: equation.constant,
+            })
+            .collect();
+
+        let domains: Vec<Vec<u8>> = letters.iter()
+            .map(|&letter| (0..batch.base)
+                .filter(|&digit| batch.domains[letter] & 1 << digit != 0)
+                .collect())
+            .collect();
+
+        let mut bounds = vec![vec![(0i128, 0i128); equations.len()]; letters.len() + 1];
+        for depth in (0..letters.len()).rev() {
+            // Letter without any digit left is never assigned, so its bounds do not matter
+            let lo = domains[depth].first().copied().unwrap_or(0) as i128;
+            let hi = domains[depth].last().copied().unwrap_or(0) as i128;
+            for (equation_idx, equation) in equations.iter().enumerate() {
+                let coefficient = equation.coefficients[depth];
+                let (rest_min, rest_max) = bounds[depth + 1][equation_idx];
+                let (term_min, term_max) = if coefficient > 0 { (coefficient * lo, coefficient * hi) } else { (coefficient * hi, coefficient * lo) };
+                bounds[depth][equation_idx] = (rest_min + term_min, rest_max + term_max);
+            }
+        }
+
+        Some(Self { batch, letters, domains, equations, bounds })
+    }
+
+    fn mapping(&self, digits: &[u8]) -> Mapping {
+        let mut mapping = [UNMAPPED; MAX_LETTERS];
+        for (&letter, &digit) in self.letters.iter().zip(digits.iter()) {
+            mapping[letter] = digit;
+        }
+        mapping
+    }
+
+    fn search(&self, depth: usize, sums: &mut [i128], used: u64, digits: &mut Vec<u8>, stats: &mut SolveStats) -> bool {
+        stats.max_depth = stats.max_depth.max(depth);
+        // Remaining letters must be able to bring every partial sum back to zero
+        let reachable = sums.iter()
+            .zip(self.bounds[depth].iter())
+            .all(|(&sum, &(lo, hi))| sum + lo <= 0 && 0 <= sum + hi);
+        if !reachable {
+            stats.pruned_column_mismatch += 1;
+            return false;
+        }
+        if depth == self.letters.len() {
+            // Products are not part of the linear equations, so complete mapping is checked as in backtracking
+            stats.permutations += 1;
+            let valid = solver_bt::check_mapping(&self.batch, &self.mapping(digits));
+            if !valid {
+                stats.pruned_column_mismatch += 1;
+            }
+            return valid;
+        }
+
+        for &digit in self.domains[depth].iter().filter(|&&digit| used & 1 << digit == 0) {
+            for (sum, equation) in sums.iter_mut().zip(self.equations.iter()) {
+                *sum += equation.coefficients[depth] * digit as i128;
+            }
+            digits.push(digit);
+            stats.nodes += 1;
+            if self.search(depth + 1, sums, used | 1 << digit, digits, stats) {
+                return true;
+            }
+            digits.pop();
+            for (sum, equation) in sums.iter_mut().zip(self.equations.iter()) {
+                *sum -= equation.coefficients[depth] * digit as i128;
+            }
+        }
+        false
+    }
+}
+
+pub fn solve(input: &str) -> Option<Solution> {
+    solve_puzzle(&Puzzle::parse(input).ok()?)
+}
+
+pub fn solve_puzzle(puzzle: &Puzzle) -> Option<Solution> {
+    solve_with(puzzle, &SolveOptions::default())
+}
+
+pub fn solve_with(puzzle: &Puzzle, options: &SolveOptions) -> Option<Solution> {
+    solve_with_stats(puzzle, options).0
+}
+
+pub fn solve_with_stats(puzzle: &Puzzle, options: &SolveOptions) -> (Option<Solution>, SolveStats) {
+    let start = Instant::now();
+    let batch = InputBatch::from_puzzle(puzzle, options);
+    // Weights or sums too big for i128 are left to backtracking
+    let Some(model) = Model::from_batch(batch) else {
+        return solver_bt::solve_with_stats(puzzle, options);
+    };
+
+    let mut stats = SolveStats::default();
+    let mut sums: Vec<i128> = model.equations.iter().map(|equation| equation.constant).collect();
+    let mut digits = vec![];
+    let solution = model.search(0, &mut sums, 0, &mut digits, &mut stats)
+        .then(|| model.batch.result(&model.mapping(&digits)));
+    stats.elapsed = start.elapsed();
+    (solution, stats)
+}
+
+#[test]
+fn coefficients_of_sum() {
+    let puzzle = Puzzle::parse("SEND + MORE == MONEY").unwrap();
+    let batch = InputBatch::from_puzzle(&puzzle, &SolveOptions::default());
+    let equation = LinearEquation::from_equation(&batch.equations[0], batch.unique_chars.len(), 10).unwrap();
+    let coefficients: HashMap<char, i128> = batch.unique_chars.iter().copied().zip(equation.coefficients).collect();
+    let expected = [('S', 1000), ('E', 91), ('N', -90), ('D', 1), ('M', -9000), ('O', -900), ('R', 10), ('Y', -1)].into_iter().collect();
+    assert_eq!(coefficients, expected);
+    assert_eq!(equation.constant, 0);
+}
+
+#[test]
+fn coefficients_with_literal_digits() {
+    let puzzle = Puzzle::parse_with("I + B9 == 1LL", &ParseOptions::new().literal_digits(true)).unwrap();
+    let batch = InputBatch::from_puzzle(&puzzle, &SolveOptions::default());
+    let equation = LinearEquation::from_equation(&batch.equations[0], batch.unique_chars.len(), 10).unwrap();
+    let coefficients: HashMap<char, i128> = batch.unique_chars.iter().copied().zip(equation.coefficients).collect();
+    assert_eq!(coefficients, [('I', 1), ('B', 10), ('L', -11)].into_iter().collect());
+    assert_eq!(equation.constant, 9 - 100);
+}
+
+#[test]
+fn stats_of_search() {
+    let (answer, stats) = solve_with_stats(&Puzzle::parse("SEND + MORE == MONEY").unwrap(), &SolveOptions::default());
+    assert!(answer.is_some());
+    assert_eq!(stats.max_depth, 8);
+    assert_eq!(stats.permutations, 1);
+    assert!(stats.pruned_column_mismatch > 0);
+}
+
+#[test]
+fn sums_which_do_not_fit_in_i128() {
+    // Weight of the 39th column fits in i128, but not times a digit
+    let input = format!("{}A + {}B == {}", "AB".repeat(19), "BA".repeat(19), "C".repeat(39));
+    let answer = solve(&input);
+    let expected = [('A', 1), ('B', 2), ('C', 3)].into_iter().collect();
+    assert_eq!(answer, Some(expected));
+}
+
+#[test]
+fn first_solution_follows_letter_order() {
+    // Letters of the same weight are tried in the letter order
+    let puzzle = Puzzle::parse("C + B == A").unwrap();
+    let expected = [('C', 1), ('B', 2), ('A', 3)].into_iter().collect();
+    assert_eq!(solve_puzzle(&puzzle), Some(expected));
+
+    let options = SolveOptions::new().letter_order(LetterOrder::Alphabetical);
+    let expected = [('A', 3), ('B', 1), ('C', 2)].into_iter().collect();
+    assert_eq!(solve_with(&puzzle, &options), Some(expected));
+}
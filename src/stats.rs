@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+/// Counters collected by a solver on the way to its answer, counters a solver does not track stay zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    /// Digits drawn for single letters.
+    pub nodes: u64,
+    /// Complete mappings checked against the domains and the whole puzzle.
+    pub permutations: u64,
+    /// Branches cut because a word would start with zero.
+    pub pruned_leading_zero: u64,
+    /// Branches cut because a column, or bounds of a whole equation, could not sum up.
+    pub pruned_column_mismatch: u64,
+    /// Deepest level of the search - columns for `solver_bt`, assigned letters for the others.
+    pub max_depth: usize,
+    pub elapsed: Duration,
+}
+
+impl SolveStats {
+    /// Adds counters of a part of the search, e.g. searched by another thread.
+    pub(crate) fn add(&mut self, other: &SolveStats) {
+        self.nodes += other.nodes;
+        self.permutations += other.permutations;
+        self.pruned_leading_zero += other.pruned_leading_zero;
+        self.pruned_column_mismatch += other.pruned_column_mismatch;
+        self.max_depth = self.max_depth.max(other.max_depth);
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+use itertools::Itertools;
+
+/// Why `solver_bt` gave up the digits drawn last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BacktrackReason {
+    /// Word would start with zero.
+    LeadingZero { letter: char },
+    /// Column cannot sum up with any carry, arithmetic of every equation is included.
+    ColumnMismatch { arithmetic: Vec<String> },
+    /// Something is carried out of the last column.
+    CarryLeft { carries: Vec<i32> },
+    /// Product equation does not hold for the whole values.
+    ProductMismatch,
+    /// Every digit of the letter was tried.
+    DigitsExhausted { letter: char },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// Search enters a column with carries from the column before, one for every equation.
+    EnterColumn { column: usize, carries_in: Vec<i32> },
+    Assign { column: usize, letter: char, digit: u8 },
+    /// Column sums up, arithmetic of every equation is included.
+    ColumnHolds { column: usize, arithmetic: Vec<String> },
+    Backtrack { column: usize, reason: BacktrackReason },
+    Solved,
+}
+
+/// Steps taken by `solver_bt`, rendered as text one step per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::EnterColumn { column, carries_in } => write!(f, "column {}, carry in {}", column, carries_in.iter().join(", ")),
+            TraceEvent::Assign { letter, digit, .. } => write!(f, "  try {} = {}", letter, digit),
+            TraceEvent::ColumnHolds { arithmetic, .. } => write!(f, "  column holds: {}", arithmetic.join("; ")),
+            TraceEvent::Backtrack { reason, .. } => match reason {
+                BacktrackReason::LeadingZero { letter } => write!(f, "  backtrack: word would start with {} = 0", letter),
+                BacktrackReason::ColumnMismatch { arithmetic } => write!(f, "  backtrack: {}", arithmetic.join("; ")),
+                BacktrackReason::CarryLeft { carries } => write!(f, "  backtrack: carry {} left after the last column", carries.iter().join(", ")),
+                BacktrackReason::ProductMismatch => write!(f, "  backtrack: product does not match"),
+                BacktrackReason::DigitsExhausted { letter } => write!(f, "  backtrack: no digit left for {}", letter),
+            },
+            TraceEvent::Solved => write!(f, "solved"),
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in self.events.iter() {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+#![allow(unused)]
+use std::{collections::HashMap, fmt};
+
+use crate::bignum::BigInt;
+use crate::options::{LeadingZeros, SolveOptions};
+use crate::puzzle::{literal_digit, ParseError, ParseOptions, Puzzle, Term};
+
+/// First rule a mapping breaks, checked in the order of the variants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    InvalidPuzzle(ParseError),
+    /// Letter of the puzzle without a digit.
+    MissingLetter(char),
+    /// Letter which is not in the puzzle.
+    UnknownLetter(char),
+    DigitOutOfRange { letter: char, digit: u8, base: u8 },
+    /// Digit the options do not allow for the letter.
+    DigitNotAllowed { letter: char, digit: u8 },
+    DuplicateDigit { digit: u8, letters: (char, char) },
+    LeadingZero { word: String },
+    /// Sides of the equation, numbered from 0, are not equal.
+    NotEqual { equation: usize, left: BigInt, right: BigInt },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::InvalidPuzzle(error) => write!(f, "invalid puzzle: {}", error),
+            VerifyError::MissingLetter(letter) => write!(f, "letter '{}' has no digit", letter),
+            VerifyError::UnknownLetter(letter) => write!(f, "letter '{}' is not in the puzzle", letter),
+            VerifyError::DigitOutOfRange { letter, digit, base } => write!(f, "digit {} of '{}' is not valid in base {}", digit, letter, base),
+            VerifyError::DigitNotAllowed { letter, digit } => write!(f, "digit {} is not allowed for '{}'", digit, letter),
+            VerifyError::DuplicateDigit { digit, letters: (first, second) } => write!(f, "'{}' and '{}' both have digit {}", first, second, digit),
+            VerifyError::LeadingZero { word } => write!(f, "word '{}' starts with zero", word),
+            VerifyError::NotEqual { equation, left, right } => write!(f, "sides of equation {} are not equal: {} != {}", equation, left, right),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Checks a solution from any source with the rules solvers enforce by default - every letter has a distinct digit,
+/// no word starts with zero and the sides are equal, evaluated with arbitrary precision.
+pub fn verify(puzzle: &str, mapping: &HashMap<char, u8>) -> Result<(), VerifyError> {
+    let puzzle = Puzzle::parse(puzzle).map_err(VerifyError::InvalidPuzzle)?;
+    verify_puzzle(&puzzle, mapping, &SolveOptions::default())
+}
+
+/// Same as `verify` for a parsed puzzle, with digits allowed and leading zeros decided by the options.
+pub fn verify_puzzle(puzzle: &Puzzle, mapping: &HashMap<char, u8>, options: &SolveOptions) -> Result<(), VerifyError> {
+    if let Some(&letter) = puzzle.unique_chars.iter().find(|letter| !mapping.contains_key(letter)) {
+        return Err(VerifyError::MissingLetter(letter));
+    }
+    if let Some(&letter) = mapping.keys().filter(|letter| !puzzle.unique_chars.contains(letter)).min() {
+        return Err(VerifyError::UnknownLetter(letter));
+    }
+
+    let digits: Vec<(char, u8)> = puzzle.unique_chars.iter().map(|&letter| (letter, mapping[&letter])).collect();
+    if let Some(&(letter, digit)) = digits.iter().find(|&&(_, digit)| digit >= puzzle.base) {
+        return Err(VerifyError::DigitOutOfRange { letter, digit, base: puzzle.base });
+    }
+    if let Some(&(letter, digit)) = digits.iter().find(|&&(letter, digit)| !options.allows(letter, digit)) {
+        return Err(VerifyError::DigitNotAllowed { letter, digit });
+    }
+    for (idx, &(letter, digit)) in digits.iter().enumerate() {
+        if let Some(&(first, _)) = digits[..idx].iter().find(|&&(_, other)| other == digit) {
+            return Err(VerifyError::DuplicateDigit { digit, letters: (first, letter) });
+        }
+    }
+
+    let value = |ch: char| literal_digit(ch).unwrap_or_else(|| mapping[&ch]);
+    if let Some(word) = puzzle.equations.iter()
+        .flat_map(|equation| equation.words())
+        .find(|word| options.forbids_leading_zero(word) && word.chars().next().map(value) == Some(0))
+    {
+        return Err(VerifyError::LeadingZero { word: word.clone() });
+    }
+
+    let side_value = |terms: &[Term]| terms.iter()
+        .map(|term| {
+            let value = term.factors.iter()
+                .map(|word| BigInt::from_digits(word.chars().map(value), puzzle.base))
+                .fold(BigInt::from(1), |product, factor| product * factor);
+            if term.negative { -value } else { value }
+        })
+        .fold(BigInt::zero(), |sum, value| sum + value);
+    for (idx, equation) in puzzle.equations.iter().enumerate() {
+        let (left, right) = (side_value(&equation.left), side_value(&equation.right));
+        if left != right {
+            return Err(VerifyError::NotEqual { equation: idx, left, right });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn mapping(digits: &[(char, u8)]) -> HashMap<char, u8> {
+    digits.iter().copied().collect()
+}
+
+#[test]
+fn valid_solution() {
+    let digits = mapping(&[('S', 9), ('E', 5), ('N', 6), ('D', 7), ('M', 1), ('O', 0), ('R', 8), ('Y', 2)]);
+    assert_eq!(verify("SEND + MORE == MONEY", &digits), Ok(()));
+}
+
+#[test]
+fn reports_broken_rule() {
+    assert_eq!(verify("A + B = ", &mapping(&[])), Err(VerifyError::InvalidPuzzle(ParseError::EmptyWord)));
+    assert_eq!(verify("A + B == C", &mapping(&[('A', 1), ('B', 2)])), Err(VerifyError::MissingLetter('C')));
+    assert_eq!(verify("A + B == C", &mapping(&[('A', 1), ('B', 2), ('C', 3), ('Z', 4), ('X', 5)])), Err(VerifyError::UnknownLetter('X')));
+    assert_eq!(verify("A + B == C", &mapping(&[('A', 1), ('B', 12), ('C', 3)])), Err(VerifyError::DigitOutOfRange { letter: 'B', digit: 12, base: 10 }));
+    assert_eq!(verify("A + B == C", &mapping(&[('A', 1), ('B', 2), ('C', 1)])), Err(VerifyError::DuplicateDigit { digit: 1, letters: ('A', 'C') }));
+    assert_eq!(verify("AB + B == C", &mapping(&[('A', 0), ('B', 2), ('C', 4)])), Err(VerifyError::LeadingZero { word: "AB".to_string() }));
+    assert_eq!(verify("A + B == C", &mapping(&[('A', 1), ('B', 2), ('C', 4)])), Err(VerifyError::NotEqual { equation: 0, left: BigInt::from(3), right: BigInt::from(4) }));
+}
+
+#[test]
+fn same_semantics_as_solver_options() {
+    let puzzle = Puzzle::parse("AS + A + O == MOM").unwrap();
+    let digits = mapping(&[('A', 9), ('S', 2), ('M', 1), ('O', 0)]);
+    assert_eq!(verify_puzzle(&puzzle, &digits, &SolveOptions::default()), Err(VerifyError::LeadingZero { word: "O".to_string() }));
+    let options = SolveOptions::new().leading_zeros(LeadingZeros::SingleLetter);
+    assert_eq!(verify_puzzle(&puzzle, &digits, &options), Ok(()));
+    assert_eq!(verify_puzzle(&puzzle, &digits, &options.fix('S', 3)), Err(VerifyError::DigitNotAllowed { letter: 'S', digit: 2 }));
+}
+
+#[test]
+fn arithmetic_with_arbitrary_precision() {
+    // 25 digits do not fit in 64 bits, 45 digits of the product do not fit in 128 bits
+    let letters = "ABCDEFGHIJ";
+    let digits: HashMap<char, u8> = letters.chars().zip(0..).collect();
+    let word = "JIHGFEDCBAJIHGFEDCBAJIHGF";
+    let value = BigInt::from_digits(word.chars().map(|ch| digits[&ch]), 10);
+    let product = value.clone() * value;
+    let product_word: String = product.to_string().chars().map(|ch| letters.chars().nth(ch.to_digit(10).unwrap() as usize).unwrap()).collect();
+    let puzzle = format!("{} * {} == {}", word, word, product_word);
+    assert_eq!(verify(&puzzle, &digits), Ok(()));
+
+    let puzzle = format!("{} * {} == {}A", word, word, &product_word[..product_word.len() - 1]);
+    assert!(matches!(verify(&puzzle, &digits), Err(VerifyError::NotEqual { equation: 0, .. })));
+}
+
+#[test]
+fn literal_digits_and_other_bases() {
+    let puzzle = Puzzle::parse_with("I + B9 == 1LL", &ParseOptions::new().literal_digits(true)).unwrap();
+    assert_eq!(verify_puzzle(&puzzle, &mapping(&[('I', 1), ('B', 9), ('L', 0)]), &SolveOptions::default()), Ok(()));
+
+    let puzzle = Puzzle::parse_with("A + A == B", &ParseOptions::new().base(3)).unwrap();
+    assert_eq!(verify_puzzle(&puzzle, &mapping(&[('A', 1), ('B', 2)]), &SolveOptions::default()), Ok(()));
+    assert_eq!(verify_puzzle(&puzzle, &mapping(&[('A', 2), ('B', 4)]), &SolveOptions::default()), Err(VerifyError::DigitOutOfRange { letter: 'B', digit: 4, base: 3 }));
+}
+
+#[test]
+fn solvers_agree_on_shared_puzzles() {
+    use crate::{solution::Solution, stats::SolveStats, solver_bf, solver_bt, solver_cp, solver_deduce, solver_lin};
+
+    type Solver = fn(&Puzzle, &SolveOptions) -> (Option<Solution>, SolveStats);
+    type Case<'a> = (&'a str, &'a ParseOptions, SolveOptions, Option<&'a [(char, u8)]>);
+    let solvers: [(&str, Solver); 5] = [
+        ("bf", solver_bf::solve_with_stats),
+        ("bt", solver_bt::solve_with_stats),
+        ("cp", solver_cp::solve_with_stats),
+        ("lin", solver_lin::solve_with_stats),
+        ("deduce", solver_deduce::solve_with_stats),
+    ];
+    let base_12 = ParseOptions::new().base(12);
+    let literal_digits = ParseOptions::new().literal_digits(true);
+    let defaults = SolveOptions::default();
+    let single_letter = SolveOptions::new().leading_zeros(LeadingZeros::SingleLetter);
+    let cases: [Case; 19] = [
+        ("I + BB == ILL", &ParseOptions::default(), defaults.clone(), Some(&[('I', 1), ('B', 9), ('L', 0)])),
+        ("A == B", &ParseOptions::default(), defaults.clone(), None),
+        ("ACA + DD == BD", &ParseOptions::default(), defaults.clone(), None),
+        ("AB + A == C", &ParseOptions::default(), defaults.clone(), None),
+        ("A + A + A + A + A + A + A + A + A + A + A + B == BCC", &ParseOptions::default(), defaults.clone(), Some(&[('A', 9), ('B', 1), ('C', 0)])),
+        ("NO + NO + TOO == LATE", &ParseOptions::default(), defaults.clone(), Some(&[('N', 7), ('O', 4), ('T', 9), ('L', 1), ('A', 0), ('E', 2)])),
+        ("SEND + MORE == MONEY", &ParseOptions::default(), defaults.clone(), Some(&[('S', 9), ('E', 5), ('N', 6), ('D', 7), ('M', 1), ('O', 0), ('R', 8), ('Y', 2)])),
+        ("SEND + MORE == MONEY", &ParseOptions::default(), SolveOptions::new().fix('E', 4), None),
+        ("SEND + MORE == MONEY", &ParseOptions::default(), SolveOptions::new().fix('E', 5).fix('N', 5), None),
+        ("SEND + MORE == MONEY", &ParseOptions::default(), SolveOptions::new().domain('E', [4]).domain('E', [5]), None),
+        ("AND + A + STRONG + OFFENSE + AS + A + GOOD == DEFENSE", &ParseOptions::default(), defaults.clone(),
+            Some(&[('A', 5), ('D', 3), ('E', 4), ('F', 7), ('G', 8), ('N', 0), ('O', 2), ('R', 1), ('S', 6), ('T', 9)])),
+        ("THIS + A + FIRE + THEREFORE + FOR + ALL + HISTORIES + I + TELL + A + TALE + THAT + FALSIFIES + ITS + TITLE + TIS + A + LIE + THE + TALE + OF + THE + LAST + FIRE + HORSES + LATE + AFTER + THE + FIRST + FATHERS + FORESEE + THE + HORRORS + THE + LAST + FREE + TROLL + TERRIFIES + THE + HORSES + OF + FIRE + THE + TROLL + RESTS + AT + THE + HOLE + OF + LOSSES + IT + IS + THERE + THAT + SHE + STORES + ROLES + OF + LEATHERS + AFTER + SHE + SATISFIES + HER + HATE + OFF + THOSE + FEARS + A + TASTE + RISES + AS + SHE + HEARS + THE + LEAST + FAR + HORSE + THOSE + FAST + HORSES + THAT + FIRST + HEAR + THE + TROLL + FLEE + OFF + TO + THE + FOREST + THE + HORSES + THAT + ALERTS + RAISE + THE + STARES + OF + THE + OTHERS + AS + THE + TROLL + ASSAILS + AT + THE + TOTAL + SHIFT + HER + TEETH + TEAR + HOOF + OFF + TORSO + AS + THE + LAST + HORSE + FORFEITS + ITS + LIFE + THE + FIRST + FATHERS + HEAR + OF + THE + HORRORS + THEIR + FEARS + THAT + THE + FIRES + FOR + THEIR + FEASTS + ARREST + AS + THE + FIRST + FATHERS + RESETTLE + THE + LAST + OF + THE + FIRE + HORSES + THE + LAST + TROLL + HARASSES + THE + FOREST + HEART + FREE + AT + LAST + OF + THE + LAST + TROLL + ALL + OFFER + THEIR + FIRE + HEAT + TO + THE + ASSISTERS + FAR + OFF + THE + TROLL + FASTS + ITS + LIFE + SHORTER + AS + STARS + RISE + THE + HORSES + REST + SAFE + AFTER + ALL + SHARE + HOT + FISH + AS + THEIR + AFFILIATES + TAILOR + A + ROOFS + FOR + THEIR + SAFE == FORTRESSES", &ParseOptions::default(), defaults.clone(),
+            Some(&[('A', 1), ('E', 0), ('F', 5), ('H', 8), ('I', 7), ('L', 2), ('O', 6), ('R', 3), ('S', 4), ('T', 9)])),
+        ("ABC * ABC == DEFABC", &ParseOptions::default(), defaults.clone(), Some(&[('A', 6), ('B', 2), ('C', 5), ('D', 3), ('E', 9), ('F', 0)])),
+        ("A + A == B, B + B == CD, D + D == E", &ParseOptions::default(), defaults.clone(), Some(&[('A', 3), ('B', 6), ('C', 1), ('D', 2), ('E', 4)])),
+        ("ABC + ABC == DB + DB + DB + D", &ParseOptions::default(), defaults.clone(), Some(&[('A', 1), ('B', 2), ('C', 7), ('D', 8)])),
+        ("A + A + A + A + A + A + A + A + A + A + A + A + A + B == BCC", &base_12, defaults.clone(), Some(&[('A', 11), ('B', 1), ('C', 0)])),
+        ("I + B9 == 1LL", &literal_digits, defaults.clone(), Some(&[('I', 1), ('B', 9), ('L', 0)])),
+        ("AS + A + O == MOM", &ParseOptions::default(), defaults.clone(), None),
+        ("AS + A + O == MOM", &ParseOptions::default(), single_letter, Some(&[('A', 9), ('S', 2), ('M', 1), ('O', 0)])),
+    ];
+
+    for (input, parse_options, options, expected) in cases {
+        let puzzle = Puzzle::parse_with(input, parse_options).unwrap();
+        let expected = expected.map(|digits| digits.iter().copied().collect::<Solution>());
+        // Brute force gets only puzzles small enough for it
+        let solvers = solvers.iter().filter(|(name, _)| *name != "bf" || puzzle.unique_chars.len() <= 6);
+        for (name, solver) in solvers {
+            let (solution, stats) = solver(&puzzle, &options);
+            assert_eq!(solution, expected, "{} {}", name, input);
+            assert!(solution.is_none() || stats.permutations >= 1, "{} {}", name, input);
+        }
+    }
+}